@@ -0,0 +1,98 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use learn_opengl_with_glium::matrices::{self, Axis, Mat4, Vec3};
+
+// the four matrices main.rs used to pass to `left_mul` every frame
+fn model_view_parts() -> [Mat4; 4] {
+    [
+        Mat4::view(
+            Vec3::new(0.0, 0.0, -2.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ),
+        Mat4::translation(0.0, 0.0, 0.8),
+        Mat4::scale(0.008),
+        Mat4::rotation(Axis::Y, 0.3),
+    ]
+}
+
+fn compose(c: &mut Criterion) {
+    let [view, translation, scale, rotation] = model_view_parts();
+    let parts = model_view_parts();
+
+    let mut group = c.benchmark_group("compose 4 matrices");
+    group.bench_function("left_mul (Vec + recursion)", |b| {
+        b.iter(|| {
+            #[allow(deprecated)]
+            matrices::left_mul(&mut vec![
+                black_box(&view),
+                black_box(&translation),
+                black_box(&scale),
+                black_box(&rotation),
+            ])
+        });
+    });
+    group.bench_function("compose (slice)", |b| {
+        b.iter(|| matrices::compose(black_box(&parts)));
+    });
+    group.bench_function("compose_n (array of refs)", |b| {
+        b.iter(|| {
+            matrices::compose_n([
+                black_box(&view),
+                black_box(&translation),
+                black_box(&scale),
+                black_box(&rotation),
+            ])
+        });
+    });
+    group.bench_function("Iterator::product", |b| {
+        b.iter(|| black_box(&parts).iter().product::<Mat4>());
+    });
+    group.finish();
+}
+
+// `product` is the SSE/NEON version with `--features simd`, the scalar one otherwise
+fn product(c: &mut Criterion) {
+    let [view, translation, ..] = model_view_parts();
+
+    let mut group = c.benchmark_group("product");
+    group.bench_function("product_scalar", |b| {
+        b.iter(|| matrices::product_scalar(black_box(&view), black_box(&translation)));
+    });
+    group.bench_function("product", |b| {
+        b.iter(|| matrices::product(black_box(&view), black_box(&translation)));
+    });
+    group.finish();
+}
+
+fn transform_points(c: &mut Criterion) {
+    let [view, translation, scale, rotation] = model_view_parts();
+    let model_view = matrices::compose_n([&view, &translation, &scale, &rotation]);
+    let points: Vec<Vec3> = (0..531_u16)
+        .map(|i| {
+            let t = f32::from(i) * 0.1;
+            Vec3::new(t.sin() * 40.0, t, t.cos() * 40.0)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("transform 531 points");
+    group.bench_function("transform_point loop", |b| {
+        b.iter(|| {
+            let mut points = points.clone();
+            for point in &mut points {
+                *point = black_box(&model_view).transform_point(*point);
+            }
+            points
+        });
+    });
+    group.bench_function("transform_points", |b| {
+        b.iter(|| {
+            let mut points = points.clone();
+            black_box(&model_view).transform_points(&mut points);
+            points
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, compose, product, transform_points);
+criterion_main!(benches);
@@ -0,0 +1,117 @@
+// Draw cost of the teapot with positions and normals in two vertex buffers, the way the scene
+// used to bind them, against one interleaved buffer. Each iteration draws the teapot `DRAWS`
+// times into an offscreen framebuffer and reads it back, so both layouts pay the same fixed
+// cost. Needs OpenGL through the headless EGL context, Linux only.
+use criterion::{criterion_group, criterion_main, Criterion};
+#[cfg(target_os = "linux")]
+use glium::{uniform, Surface};
+
+#[cfg(target_os = "linux")]
+fn draw_teapot(c: &mut Criterion) {
+    use learn_opengl_with_glium::camera::Camera;
+    use learn_opengl_with_glium::headless::Headless;
+    use learn_opengl_with_glium::matrices::{Projection, Vec3};
+    use learn_opengl_with_glium::mesh::vertex;
+    use learn_opengl_with_glium::scene::{self, BACKFACE_CULLING, CLEAR_COLOR, LIGHT};
+    use learn_opengl_with_glium::{shaders, teapot};
+    use std::f32::consts::TAU;
+
+    const SIZE: u32 = 256;
+    const DRAWS: usize = 50;
+
+    let headless = match Headless::new() {
+        Ok(headless) => headless,
+        Err(error) => {
+            eprintln!("skipping the vertex layout benchmark, no headless context: {error}");
+            return;
+        }
+    };
+    let positions = glium::VertexBuffer::new(&headless, &teapot::VERTICES).unwrap();
+    let normals = glium::VertexBuffer::new(&headless, &teapot::NORMALS).unwrap();
+    let interleaved = glium::VertexBuffer::new(
+        &headless,
+        &vertex::interleave(&teapot::VERTICES, &teapot::NORMALS),
+    )
+    .unwrap();
+    let indices = glium::IndexBuffer::new(
+        &headless,
+        glium::index::PrimitiveType::TrianglesList,
+        &teapot::INDICES,
+    )
+    .unwrap();
+    let program = glium::Program::from_source(
+        &headless,
+        shaders::VERTEX_SHADER,
+        shaders::FRAGMENT_SHADER,
+        None,
+    )
+    .unwrap();
+
+    let mut camera = Camera::new(
+        Vec3::new(0.0, 0.0, -2.0),
+        Projection::Perspective {
+            fov_angle: TAU / 6.0,
+            z_near: 0.1,
+            z_far: 1024.0,
+        },
+    );
+    camera.set_viewport(SIZE, SIZE);
+    let model_view = camera.view_matrix() * scene::teapot_transform(0.3).matrix();
+    let uniforms = uniform! {
+        u_light: LIGHT,
+        u_perspective: camera.projection_matrix(),
+        model_view: model_view,
+        u_normal_matrix: model_view.normal_matrix().unwrap(),
+    };
+    let (depth_test, clear_depth) = scene::depth_test(&camera);
+    let parameters = glium::DrawParameters {
+        depth: glium::Depth {
+            test: depth_test,
+            write: true,
+            ..glium::Depth::default()
+        },
+        backface_culling: BACKFACE_CULLING,
+        ..glium::DrawParameters::default()
+    };
+
+    let mut group = c.benchmark_group(format!("draw the teapot {DRAWS} times"));
+    group.bench_function("split position and normal buffers", |b| {
+        b.iter(|| {
+            headless.render(SIZE, SIZE, |target| {
+                target.clear_color_and_depth(CLEAR_COLOR, clear_depth);
+                for _ in 0..DRAWS {
+                    target
+                        .draw(
+                            (&positions, &normals),
+                            &indices,
+                            &program,
+                            &uniforms,
+                            &parameters,
+                        )
+                        .unwrap();
+                }
+            })
+        });
+    });
+    group.bench_function("interleaved buffer", |b| {
+        b.iter(|| {
+            headless.render(SIZE, SIZE, |target| {
+                target.clear_color_and_depth(CLEAR_COLOR, clear_depth);
+                for _ in 0..DRAWS {
+                    target
+                        .draw(&interleaved, &indices, &program, &uniforms, &parameters)
+                        .unwrap();
+                }
+            })
+        });
+    });
+    group.finish();
+}
+
+#[cfg(not(target_os = "linux"))]
+fn draw_teapot(_: &mut Criterion) {
+    eprintln!("skipping the vertex layout benchmark, headless OpenGL is Linux only");
+}
+
+criterion_group!(benches, draw_teapot);
+criterion_main!(benches);
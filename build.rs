@@ -0,0 +1,178 @@
+#![warn(clippy::all, clippy::pedantic, clippy::nursery)]
+
+// Bakes every `models/<name>.obj` into `$OUT_DIR/models/<name>.rs`: `VERTICES`, `NORMALS` and
+// `INDICES` arrays in the layout of `src/teapot.rs`, which includes the teapot's. Another model
+// only needs a module that includes its file the same way.
+//
+// The arrays keep OBJ's numbering from 1, with a zero vertex 0, and the faces' corners become
+// vertices in the order of their position and normal numbers, so a file whose faces use the
+// same number for both keeps its vertex order. Only `v`, `vn` and `f` are read. Faces have to be
+// triangles, turning polygons into triangles is left to `mesh::obj`, which would read the same
+// file differently than any simpler triangulation here. Faces that aren't triangles or refer to
+// missing elements, or more vertices than `u16` indices can reach, fail the build.
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // the directory for models that are added or removed, each file for edits to it
+    println!("cargo:rerun-if-changed=models");
+
+    let out_dir = std::env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    let baked_dir = Path::new(&out_dir).join("models");
+    std::fs::create_dir_all(&baked_dir).expect("couldn't create the baked models' directory");
+
+    let mut models: Vec<_> = std::fs::read_dir("models")
+        .expect("couldn't list models/")
+        .map(|entry| entry.expect("couldn't list models/").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "obj"))
+        .collect();
+    models.sort();
+    for path in models {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let source = std::fs::read_to_string(&path)
+            .unwrap_or_else(|error| panic!("couldn't read {}: {error}", path.display()));
+        let baked = bake(&source).unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+        let name = path
+            .file_stem()
+            .expect("a file with an extension has a stem");
+        let baked_path = baked_dir.join(name).with_extension("rs");
+        std::fs::write(&baked_path, baked)
+            .unwrap_or_else(|error| panic!("couldn't write {}: {error}", baked_path.display()));
+    }
+}
+
+// indices into the position and normal lists, from 0
+type Corner = (usize, Option<usize>);
+
+// the Rust source of the arrays, errors about a malformed line start with its number
+fn bake(source: &str) -> Result<String, String> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut faces: Vec<[Corner; 3]> = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let error = |message: String| format!("{}: {message}", number + 1);
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next();
+        let arguments: Vec<&str> = tokens.collect();
+        match keyword {
+            Some("v") => positions.push(vector(&arguments).map_err(error)?),
+            Some("vn") => normals.push(vector(&arguments).map_err(error)?),
+            Some("f") => {
+                if arguments.len() != 3 {
+                    return Err(error(format!(
+                        "faces have to be triangles, found {} corners",
+                        arguments.len()
+                    )));
+                }
+                let mut face = [(0, None); 3];
+                for (corner, argument) in face.iter_mut().zip(&arguments) {
+                    *corner =
+                        parse_corner(argument, positions.len(), normals.len()).map_err(error)?;
+                }
+                faces.push(face);
+            }
+            _ => (),
+        }
+    }
+
+    // numbered from 1 in sorted order, 0 is the zero vertex
+    let mut vertices: BTreeMap<Corner, u16> = faces.iter().flatten().map(|&c| (c, 0)).collect();
+    if vertices.len() > usize::from(u16::MAX) {
+        return Err(format!(
+            "{} vertices don't fit in u16 indices",
+            vertices.len()
+        ));
+    }
+    for (number, index) in (1..).zip(vertices.values_mut()) {
+        *index = number;
+    }
+
+    let mut baked = String::from("// baked by build.rs, don't edit\n");
+    let vertex_count = vertices.len() + 1;
+    writeln!(baked, "pub const VERTICES: [Vertex; {vertex_count}] = [").unwrap();
+    writeln!(baked, "    Vertex::new(crate::matrices::Vec3::ZERO),").unwrap();
+    for &(position, _) in vertices.keys() {
+        let [x, y, z] = positions[position];
+        writeln!(
+            baked,
+            "    Vertex::new(crate::matrices::Vec3::new({x:?}, {y:?}, {z:?})),"
+        )
+        .unwrap();
+    }
+    writeln!(baked, "];\n").unwrap();
+
+    writeln!(baked, "pub const NORMALS: [Normal; {vertex_count}] = [").unwrap();
+    writeln!(baked, "    Normal::new(crate::matrices::Vec3::ZERO),").unwrap();
+    for &(_, normal) in vertices.keys() {
+        let [x, y, z] = normal.map_or([0.0; 3], |normal| normals[normal]);
+        writeln!(
+            baked,
+            "    Normal::new(crate::matrices::Vec3::new({x:?}, {y:?}, {z:?})),"
+        )
+        .unwrap();
+    }
+    writeln!(baked, "];\n").unwrap();
+
+    writeln!(baked, "pub const INDICES: [u16; {}] = [", faces.len() * 3).unwrap();
+    for face in &faces {
+        let [a, b, c] = face.map(|corner| vertices[&corner]);
+        writeln!(baked, "    {a}, {b}, {c},").unwrap();
+    }
+    writeln!(baked, "];\n").unwrap();
+    writeln!(
+        baked,
+        "const _: () = check_indices(&INDICES, VERTICES.len(), NORMALS.len());"
+    )
+    .unwrap();
+    Ok(baked)
+}
+
+fn vector(arguments: &[&str]) -> Result<[f32; 3], String> {
+    // `v` can have a fourth weight, which isn't used
+    let [x, y, z, ..] = arguments else {
+        return Err(format!("expected 3 numbers, found {}", arguments.len()));
+    };
+    let mut vector = [0.0; 3];
+    for (value, argument) in vector.iter_mut().zip([x, y, z]) {
+        *value = argument
+            .parse()
+            .ok()
+            .filter(|value: &f32| value.is_finite())
+            .ok_or_else(|| format!("`{argument}` isn't a finite number"))?;
+    }
+    Ok(vector)
+}
+
+// `v`, `v/vt`, `v//vn` or `v/vt/vn`, the texture coordinate is ignored
+fn parse_corner(corner: &str, positions: usize, normals: usize) -> Result<Corner, String> {
+    let mut parts = corner.split('/');
+    let position = resolve(parts.next().unwrap_or_default(), positions, "position")?;
+    let normal = parts
+        .nth(1)
+        .filter(|part| !part.is_empty())
+        .map(|normal| resolve(normal, normals, "normal"))
+        .transpose()?;
+    Ok((position, normal))
+}
+
+// 1-based, or negative to count back from the last element read so far
+fn resolve(index: &str, count: usize, what: &str) -> Result<usize, String> {
+    let number: i64 = index
+        .parse()
+        .map_err(|_| format!("`{index}` isn't a {what} index"))?;
+    let resolved = if number < 0 {
+        usize::try_from(number.unsigned_abs())
+            .ok()
+            .and_then(|back| count.checked_sub(back))
+    } else {
+        usize::try_from(number)
+            .ok()
+            .and_then(|number| number.checked_sub(1))
+    };
+    resolved
+        .filter(|&resolved| resolved < count)
+        .ok_or_else(|| format!("{what} {index} doesn't exist, there are {count}"))
+}
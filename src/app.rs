@@ -0,0 +1,152 @@
+use crate::clock::{Clock, Frame};
+use glium::glutin::event::{Event, WindowEvent};
+use glium::glutin::event_loop::{ControlFlow, EventLoop};
+use glium::glutin::window::WindowBuilder;
+use glium::glutin::ContextBuilder;
+use glium::Display;
+use std::time::{Duration, Instant};
+
+// redraws are capped to this rate while animating, input and resizes redraw right away
+const FRAME_PERIOD: Duration = Duration::from_nanos(1_000_000_000 / 60);
+// step of `Frame::fixed_steps`
+const FIXED_STEP: Duration = Duration::from_nanos(1_000_000_000 / 120);
+
+// a demo driven by `run`, which owns the window, the event loop and the frame timing
+pub trait App {
+    // every window and device event, after `run` has handled closing, resizing and focus
+    fn on_event(&mut self, _context: &mut Context, _event: &Event<'_, ()>) {}
+
+    // once per drawn frame, before `render`
+    fn update(&mut self, context: &mut Context, frame: &Frame);
+
+    // draws the whole frame, clearing included
+    fn render(&mut self, context: &Context, target: &mut glium::Frame);
+}
+
+// what `run` shares with the app
+pub struct Context {
+    pub display: Display,
+    pub clock: Clock,
+    exit_requested: bool,
+}
+
+impl Context {
+    // leaves the event loop once the current events are handled
+    pub const fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+}
+
+// state of the window as far as scheduling frames goes
+#[allow(clippy::struct_excessive_bools)]
+struct Schedule {
+    focused: bool,
+    minimized: bool,
+    redraw_needed: bool,
+    next_frame: Instant,
+    last_title_update: Instant,
+}
+
+impl Schedule {
+    // animation only runs for a visible, focused window
+    const fn active(&self) -> bool {
+        self.focused && !self.minimized
+    }
+}
+
+/// Opens a window titled `title` with a 24 bit depth buffer and runs the app built by `init` in it.
+///
+/// # Panics
+/// If the window or its OpenGL context can't be created.
+pub fn run<A, F>(title: &str, window: WindowBuilder, init: F) -> !
+where
+    A: App + 'static,
+    F: FnOnce(&Display) -> A,
+{
+    let event_loop = EventLoop::new();
+    let context_builder = ContextBuilder::new().with_depth_buffer(24);
+    let display = Display::new(window.with_title(title), context_builder, &event_loop).unwrap();
+
+    let mut app = init(&display);
+    let mut context = Context {
+        display,
+        clock: Clock::new(FIXED_STEP),
+        exit_requested: false,
+    };
+    let title = title.to_owned();
+    let mut schedule = Schedule {
+        focused: true,
+        minimized: false,
+        redraw_needed: true,
+        next_frame: Instant::now(),
+        last_title_update: Instant::now(),
+    };
+
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent {
+            event: ref window_event,
+            ..
+        } => {
+            let was_active = schedule.active();
+            match window_event {
+                WindowEvent::CloseRequested => context.exit(),
+                WindowEvent::Resized(size) => {
+                    schedule.minimized = size.width == 0 || size.height == 0;
+                }
+                WindowEvent::Focused(focused) => schedule.focused = *focused,
+                _ => (),
+            }
+            if !was_active && schedule.active() {
+                // don't let the time spent in the background jump the animation forward
+                context.clock.restart();
+            }
+            schedule.redraw_needed = true;
+            app.on_event(&mut context, &event);
+        }
+        Event::DeviceEvent { .. } => app.on_event(&mut context, &event),
+        Event::MainEventsCleared => {
+            let animating = schedule.active() && Instant::now() >= schedule.next_frame;
+            if !schedule.minimized && (schedule.redraw_needed || animating) {
+                context.display.gl_window().window().request_redraw();
+            }
+        }
+        Event::RedrawRequested(_) => {
+            schedule.redraw_needed = false;
+            schedule.next_frame = Instant::now() + FRAME_PERIOD;
+            if !schedule.active() {
+                context.clock.restart();
+            }
+            let frame = context.clock.tick();
+
+            // frame stats in the window title, refreshed once a second
+            if schedule.last_title_update.elapsed() >= Duration::from_secs(1) {
+                schedule.last_title_update = Instant::now();
+                let paused = if context.clock.is_paused() {
+                    ", paused"
+                } else {
+                    ""
+                };
+                context.display.gl_window().window().set_title(&format!(
+                    "{title} | {} | time scale {}{paused}",
+                    context.clock.stats(),
+                    context.clock.time_scale
+                ));
+            }
+
+            app.update(&mut context, &frame);
+            let mut target = context.display.draw();
+            app.render(&context, &mut target);
+            target.finish().unwrap();
+        }
+        Event::RedrawEventsCleared => {
+            *control_flow = if context.exit_requested {
+                ControlFlow::Exit
+            } else if schedule.active() {
+                ControlFlow::WaitUntil(schedule.next_frame)
+            } else {
+                ControlFlow::Wait
+            };
+        }
+        _ => (),
+    })
+}
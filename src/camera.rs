@@ -0,0 +1,131 @@
+use crate::matrices::{Mat4, Projection, Quat, Vec3};
+
+pub mod fly;
+pub mod orbit;
+
+// how far the controllers let the camera look up or down: just short of straight up/down, so
+// `Camera::look_at` never has to pick a fallback up vector
+const MAX_PITCH: f32 = std::f32::consts::TAU / 4.0 - 0.01;
+
+// a camera looks along its local +Z with +Y up, the same convention `Mat4::view` uses
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub projection: Projection,
+    // width / height
+    pub aspect_ratio: f32,
+}
+
+impl Camera {
+    // looking along the world +Z
+    #[must_use]
+    pub const fn new(position: Vec3, projection: Projection) -> Self {
+        Self {
+            position,
+            orientation: Quat::IDENTITY,
+            projection,
+            aspect_ratio: 1.0,
+        }
+    }
+
+    #[must_use]
+    pub fn looking_at(mut self, target: Vec3) -> Self {
+        self.look_at(target);
+        self
+    }
+
+    #[must_use]
+    pub fn forward(&self) -> Vec3 {
+        self.orientation * Vec3::Z
+    }
+
+    #[must_use]
+    pub fn up(&self) -> Vec3 {
+        self.orientation * Vec3::Y
+    }
+
+    #[must_use]
+    pub fn right(&self) -> Vec3 {
+        self.orientation * Vec3::X
+    }
+
+    // turns the camera towards `target`, keeping the world +Y up.
+    // Does nothing if `target` is the camera position; looking straight up or down keeps the
+    // current right vector instead of the world up.
+    pub fn look_at(&mut self, target: Vec3) {
+        let direction = target - self.position;
+        if direction.length() <= f32::EPSILON {
+            return;
+        }
+        let up = if direction.normalized().cross(Vec3::Y).length() <= 1e-6 {
+            direction.cross(self.right())
+        } else {
+            Vec3::Y
+        };
+        self.orientation = Quat::look_rotation(direction, up);
+    }
+
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            #[allow(clippy::cast_precision_loss)]
+            let aspect_ratio = width as f32 / height as f32;
+            self.aspect_ratio = aspect_ratio;
+        }
+    }
+
+    #[must_use]
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::view(self.position, self.forward(), self.up())
+    }
+
+    #[must_use]
+    pub fn projection_matrix(&self) -> Mat4 {
+        self.projection.matrix(self.aspect_ratio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    const PROJECTION: Projection = Projection::Perspective {
+        fov_angle: TAU / 6.0,
+        z_near: 0.1,
+        z_far: 100.0,
+    };
+
+    #[test]
+    fn look_at_centers_the_target() {
+        let targets = [
+            Vec3::new(0.0, 0.0, 5.0),
+            Vec3::new(3.0, -1.0, 0.5),
+            Vec3::new(-4.0, 2.0, -7.0),
+            Vec3::new(0.0, 10.0, 0.0),
+        ];
+        for target in targets {
+            let camera = Camera::new(Vec3::new(0.5, 1.0, -2.0), PROJECTION).looking_at(target);
+            let in_view = camera.view_matrix().transform_point(target);
+            assert!(
+                in_view.x.abs() < 1e-4 && in_view.y.abs() < 1e-4,
+                "{target:?}: {in_view:?}"
+            );
+            assert!(in_view.z > 0.0, "{target:?} is behind the camera");
+        }
+    }
+
+    #[test]
+    fn look_at_keeps_the_world_up() {
+        let camera = Camera::new(Vec3::ZERO, PROJECTION).looking_at(Vec3::new(3.0, 1.0, 2.0));
+        assert!(camera.right().y.abs() < 1e-5);
+        assert!(camera.up().y > 0.0);
+    }
+
+    #[test]
+    fn default_camera_matches_the_old_hardcoded_view() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, -2.0), PROJECTION);
+        let expected = Mat4::view(Vec3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 2.0), Vec3::Y);
+        assert_eq!(camera.view_matrix(), expected);
+    }
+}
@@ -0,0 +1,278 @@
+use super::{Camera, MAX_PITCH};
+use crate::input::Input;
+use crate::matrices::{Quat, Vec3};
+use glium::glutin::event::{MouseButton, VirtualKeyCode};
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+// first person camera: WASD to move, Space/C to go up/down, hold shift to go faster;
+// click to grab the cursor and look around with the mouse, Escape releases it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlyController {
+    // rotation about the world Y axis, 0 looks along +Z
+    pub yaw: f32,
+    // positive looks down
+    pub pitch: f32,
+    // units per second
+    pub speed: f32,
+    // speed multiplier while shift is held
+    pub boost: f32,
+    // radians per unit of raw mouse motion
+    pub sensitivity: f32,
+    held: Held,
+    cursor_grabbed: bool,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+struct Held {
+    forward: bool,
+    back: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+    boost: bool,
+}
+
+impl FlyController {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            speed: 2.0,
+            boost: 4.0,
+            sensitivity: 0.002,
+            held: Held {
+                forward: false,
+                back: false,
+                left: false,
+                right: false,
+                up: false,
+                down: false,
+                boost: false,
+            },
+            cursor_grabbed: false,
+        }
+    }
+
+    // continues from wherever `camera` is looking
+    #[must_use]
+    pub fn from_camera(camera: &Camera) -> Self {
+        let forward = camera.forward();
+        Self {
+            yaw: forward.x.atan2(forward.z),
+            pitch: (-forward.y)
+                .clamp(-1.0, 1.0)
+                .asin()
+                .clamp(-MAX_PITCH, MAX_PITCH),
+            ..Self::new()
+        }
+    }
+
+    // the window should grab and hide the cursor while this is true
+    #[must_use]
+    pub const fn cursor_grabbed(&self) -> bool {
+        self.cursor_grabbed
+    }
+
+    pub fn handle_input(&mut self, input: &Input) {
+        match *input {
+            Input::MouseButton {
+                button: MouseButton::Left,
+                pressed: true,
+            } => self.cursor_grabbed = true,
+            Input::MouseMotion { dx, dy } if self.cursor_grabbed => {
+                #[allow(clippy::cast_possible_truncation)]
+                let (dx, dy) = (dx as f32, dy as f32);
+                self.yaw = dx.mul_add(self.sensitivity, self.yaw).rem_euclid(TAU);
+                self.pitch = dy
+                    .mul_add(self.sensitivity, self.pitch)
+                    .clamp(-MAX_PITCH, MAX_PITCH);
+            }
+            Input::Key {
+                key: VirtualKeyCode::Escape,
+                pressed: true,
+            } => self.cursor_grabbed = false,
+            Input::Key { key, pressed } => self.handle_key(key, pressed),
+            Input::Focused(false) => {
+                self.held = Held::default();
+                self.cursor_grabbed = false;
+            }
+            _ => (),
+        }
+    }
+
+    const fn handle_key(&mut self, key: VirtualKeyCode, pressed: bool) {
+        let held = match key {
+            VirtualKeyCode::W => &mut self.held.forward,
+            VirtualKeyCode::S => &mut self.held.back,
+            VirtualKeyCode::A => &mut self.held.left,
+            VirtualKeyCode::D => &mut self.held.right,
+            VirtualKeyCode::Space => &mut self.held.up,
+            VirtualKeyCode::C => &mut self.held.down,
+            VirtualKeyCode::LShift | VirtualKeyCode::RShift => &mut self.held.boost,
+            _ => return,
+        };
+        *held = pressed;
+    }
+
+    fn orientation(&self) -> Quat {
+        Quat::from_axis_angle(Vec3::Y, self.yaw) * Quat::from_axis_angle(Vec3::X, self.pitch)
+    }
+
+    // moves by `speed * frame_time` along the held directions, so the distance covered
+    // doesn't depend on how often this is called
+    pub fn update_camera(&self, camera: &mut Camera, frame_time: Duration) {
+        camera.orientation = self.orientation();
+
+        let axis =
+            |positive: bool, negative: bool| f32::from(i8::from(positive) - i8::from(negative));
+        let direction = camera.forward() * axis(self.held.forward, self.held.back)
+            + camera.right() * axis(self.held.right, self.held.left)
+            + Vec3::Y * axis(self.held.up, self.held.down);
+        if direction.length() == 0.0 {
+            return;
+        }
+
+        let speed = if self.held.boost {
+            self.speed * self.boost
+        } else {
+            self.speed
+        };
+        camera.position =
+            camera.position + direction.normalized() * (speed * frame_time.as_secs_f32());
+    }
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::suboptimal_flops)]
+mod tests {
+    use super::*;
+    use crate::matrices::Projection;
+
+    fn camera() -> Camera {
+        Camera::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            Projection::Perspective {
+                fov_angle: TAU / 6.0,
+                z_near: 0.1,
+                z_far: 100.0,
+            },
+        )
+    }
+
+    const fn key(key: VirtualKeyCode, pressed: bool) -> Input {
+        Input::Key { key, pressed }
+    }
+
+    #[test]
+    fn from_camera_keeps_the_view() {
+        let camera = camera().looking_at(Vec3::new(3.0, -1.0, 4.0));
+        let mut moved = camera;
+        FlyController::from_camera(&camera).update_camera(&mut moved, Duration::ZERO);
+        assert!((moved.forward() - camera.forward()).length() < 1e-5);
+        assert_eq!(moved.position, camera.position);
+    }
+
+    #[test]
+    fn held_key_moves_at_speed() {
+        let mut fly = FlyController::new();
+        let mut camera = camera();
+        fly.handle_input(&key(VirtualKeyCode::W, true));
+        fly.update_camera(&mut camera, Duration::from_millis(500));
+        assert!((camera.position - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-5);
+
+        fly.handle_input(&key(VirtualKeyCode::W, false));
+        fly.update_camera(&mut camera, Duration::from_millis(500));
+        assert!((camera.position - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn movement_is_frame_rate_independent() {
+        let mut fly = FlyController::new();
+        fly.handle_input(&key(VirtualKeyCode::D, true));
+        fly.handle_input(&key(VirtualKeyCode::Space, true));
+
+        let mut one_frame = camera();
+        fly.update_camera(&mut one_frame, Duration::from_secs(1));
+        let mut many_frames = camera();
+        for _ in 0..144 {
+            fly.update_camera(&mut many_frames, Duration::from_secs(1) / 144);
+        }
+        assert!((one_frame.position - many_frames.position).length() < 1e-4);
+        // diagonal movement isn't faster than straight movement
+        assert!(((one_frame.position - camera().position).length() - fly.speed).abs() < 1e-5);
+    }
+
+    #[test]
+    fn boost_multiplies_speed() {
+        let mut fly = FlyController::new();
+        let mut camera = camera();
+        fly.handle_input(&key(VirtualKeyCode::S, true));
+        fly.handle_input(&key(VirtualKeyCode::LShift, true));
+        fly.update_camera(&mut camera, Duration::from_secs(1));
+        assert!((camera.position.z - (-2.0 - fly.speed * fly.boost)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn mouse_look_needs_a_grabbed_cursor() {
+        let mut fly = FlyController::new();
+        fly.handle_input(&Input::MouseMotion { dx: 100.0, dy: 0.0 });
+        assert!(fly.yaw.abs() < f32::EPSILON);
+
+        fly.handle_input(&Input::MouseButton {
+            button: MouseButton::Left,
+            pressed: true,
+        });
+        assert!(fly.cursor_grabbed());
+        fly.handle_input(&Input::MouseMotion {
+            dx: 100.0,
+            dy: 50.0,
+        });
+        assert!((fly.yaw - 100.0 * fly.sensitivity).abs() < 1e-6);
+        assert!((fly.pitch - 50.0 * fly.sensitivity).abs() < 1e-6);
+
+        // looking right and down
+        let mut camera = camera();
+        fly.update_camera(&mut camera, Duration::ZERO);
+        assert!(camera.forward().x > 0.0 && camera.forward().y < 0.0);
+
+        fly.handle_input(&key(VirtualKeyCode::Escape, true));
+        assert!(!fly.cursor_grabbed());
+    }
+
+    #[test]
+    fn pitch_is_clamped() {
+        let mut fly = FlyController::new();
+        fly.handle_input(&Input::MouseButton {
+            button: MouseButton::Left,
+            pressed: true,
+        });
+        fly.handle_input(&Input::MouseMotion { dx: 0.0, dy: -1e6 });
+        assert!((fly.pitch + MAX_PITCH).abs() < 1e-6);
+    }
+
+    #[test]
+    fn losing_focus_releases_keys_and_cursor() {
+        let mut fly = FlyController::new();
+        fly.handle_input(&Input::MouseButton {
+            button: MouseButton::Left,
+            pressed: true,
+        });
+        fly.handle_input(&key(VirtualKeyCode::W, true));
+        fly.handle_input(&Input::Focused(false));
+        assert!(!fly.cursor_grabbed());
+
+        let mut camera = camera();
+        fly.update_camera(&mut camera, Duration::from_secs(1));
+        assert_eq!(camera.position, Vec3::new(0.0, 0.0, -2.0));
+    }
+}
@@ -0,0 +1,341 @@
+use super::{Camera, MAX_PITCH};
+use crate::input::Input;
+use crate::matrices::{Quat, Vec3};
+use glium::glutin::event::{MouseButton, VirtualKeyCode};
+use std::f32::consts::TAU;
+
+// keeps the camera on a sphere around `target`:
+// left drag or the arrow keys orbit, right drag pans the target, the wheel or +/- zoom
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    // rotation about the world Y axis, 0 puts the camera on the -Z side of the target
+    pub yaw: f32,
+    // positive puts the camera above the target
+    pub pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    // radians per pixel of drag
+    pub rotate_sensitivity: f32,
+    // fraction of the distance per pixel of drag
+    pub pan_sensitivity: f32,
+    // fraction of the distance per scroll line or key press
+    pub zoom_sensitivity: f32,
+    // radians per arrow key press
+    pub key_step: f32,
+    drag: Option<Drag>,
+    cursor: Option<(f64, f64)>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Drag {
+    Rotate,
+    Pan,
+}
+
+impl OrbitController {
+    #[must_use]
+    pub const fn new(target: Vec3, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            min_distance: 0.1,
+            max_distance: 100.0,
+            rotate_sensitivity: 0.01,
+            pan_sensitivity: 0.002,
+            zoom_sensitivity: 0.1,
+            key_step: TAU / 72.0,
+            drag: None,
+            cursor: None,
+        }
+    }
+
+    // places the controller where `camera` is, orbiting `target`
+    #[must_use]
+    pub fn around(camera: &Camera, target: Vec3) -> Self {
+        let offset = camera.position - target;
+        let distance = offset.length();
+        let mut controller = Self::new(target, distance);
+        controller.yaw = (-offset.x).atan2(-offset.z);
+        controller.pitch = (offset.y / distance).asin();
+        controller
+    }
+
+    // returns whether the input changed the camera placement
+    pub fn handle_input(&mut self, input: &Input) -> bool {
+        match *input {
+            Input::MouseButton { button, pressed } => {
+                let drag = match button {
+                    MouseButton::Left => Drag::Rotate,
+                    MouseButton::Right => Drag::Pan,
+                    _ => return false,
+                };
+                if pressed {
+                    self.drag = Some(drag);
+                } else if self.drag == Some(drag) {
+                    self.drag = None;
+                }
+                false
+            }
+            Input::CursorMoved { x, y } => {
+                let previous = self.cursor.replace((x, y));
+                match (self.drag, previous) {
+                    (Some(drag), Some((previous_x, previous_y))) => {
+                        #[allow(clippy::cast_possible_truncation)]
+                        let (dx, dy) = ((x - previous_x) as f32, (y - previous_y) as f32);
+                        match drag {
+                            Drag::Rotate => self.rotate(dx, dy),
+                            Drag::Pan => self.pan(dx, dy),
+                        }
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Input::CursorLeft | Input::Focused(false) => {
+                self.drag = None;
+                self.cursor = None;
+                false
+            }
+            Input::Scroll { lines } => {
+                self.zoom(lines);
+                true
+            }
+            Input::Key { key, pressed: true } => self.handle_key(key),
+            Input::Key { .. } | Input::MouseMotion { .. } | Input::Focused(true) => false,
+        }
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode) -> bool {
+        let step = self.key_step / self.rotate_sensitivity;
+        match key {
+            VirtualKeyCode::Left => self.rotate(-step, 0.0),
+            VirtualKeyCode::Right => self.rotate(step, 0.0),
+            VirtualKeyCode::Up => self.rotate(0.0, -step),
+            VirtualKeyCode::Down => self.rotate(0.0, step),
+            VirtualKeyCode::Equals | VirtualKeyCode::Plus | VirtualKeyCode::NumpadAdd => {
+                self.zoom(1.0);
+            }
+            VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => self.zoom(-1.0),
+            _ => return false,
+        }
+        true
+    }
+
+    // dragging right swings the camera to the right around the target, dragging up raises it
+    fn rotate(&mut self, dx: f32, dy: f32) {
+        self.yaw = dx
+            .mul_add(-self.rotate_sensitivity, self.yaw)
+            .rem_euclid(TAU);
+        self.pitch = dy
+            .mul_add(-self.rotate_sensitivity, self.pitch)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    // moves the target in the view plane so the scene follows the cursor
+    fn pan(&mut self, dx: f32, dy: f32) {
+        let orientation = self.orientation();
+        let scale = self.distance * self.pan_sensitivity;
+        self.target = self.target - orientation * Vec3::X * (dx * scale)
+            + orientation * Vec3::Y * (dy * scale);
+    }
+
+    // positive `lines` move closer
+    fn zoom(&mut self, lines: f32) {
+        let factor = (1.0 - self.zoom_sensitivity).powf(lines);
+        self.distance = (self.distance * factor).clamp(self.min_distance, self.max_distance);
+    }
+
+    fn orientation(&self) -> Quat {
+        Quat::from_axis_angle(Vec3::Y, self.yaw) * Quat::from_axis_angle(Vec3::X, self.pitch)
+    }
+
+    #[must_use]
+    pub fn position(&self) -> Vec3 {
+        self.target - self.orientation() * Vec3::Z * self.distance
+    }
+
+    pub fn update_camera(&self, camera: &mut Camera) {
+        camera.position = self.position();
+        camera.look_at(self.target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrices::Projection;
+
+    fn camera() -> Camera {
+        Camera::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            Projection::Perspective {
+                fov_angle: TAU / 6.0,
+                z_near: 0.1,
+                z_far: 100.0,
+            },
+        )
+    }
+
+    fn feed(controller: &mut OrbitController, inputs: &[Input]) {
+        for input in inputs {
+            controller.handle_input(input);
+        }
+    }
+
+    const fn button(button: MouseButton, pressed: bool) -> Input {
+        Input::MouseButton { button, pressed }
+    }
+
+    const fn cursor(x: f64, y: f64) -> Input {
+        Input::CursorMoved { x, y }
+    }
+
+    #[test]
+    fn around_reproduces_the_camera_position() {
+        let target = Vec3::new(0.0, 0.0, 0.8);
+        let mut camera = camera();
+        let controller = OrbitController::around(&camera, target);
+        assert!((controller.position() - camera.position).length() < 1e-5);
+
+        camera.position = Vec3::new(1.0, 2.0, 3.0);
+        let controller = OrbitController::around(&camera, target);
+        assert!((controller.position() - camera.position).length() < 1e-5);
+    }
+
+    #[test]
+    fn cursor_moves_without_a_button_do_nothing() {
+        let mut controller = OrbitController::new(Vec3::ZERO, 3.0);
+        let before = controller;
+        feed(&mut controller, &[cursor(10.0, 10.0), cursor(200.0, 50.0)]);
+        assert_eq!(controller.position(), before.position());
+    }
+
+    #[test]
+    fn left_drag_orbits_at_constant_distance() {
+        let mut controller = OrbitController::new(Vec3::new(0.0, 0.0, 0.8), 2.8);
+        let before = controller.position();
+        feed(
+            &mut controller,
+            &[
+                cursor(100.0, 100.0),
+                button(MouseButton::Left, true),
+                cursor(150.0, 80.0),
+                cursor(180.0, 60.0),
+                button(MouseButton::Left, false),
+                cursor(400.0, 400.0),
+            ],
+        );
+        let after = controller.position();
+        assert!(after != before);
+        assert!(((after - controller.target).length() - 2.8).abs() < 1e-5);
+        // dragging up raised the camera above the target
+        assert!(after.y > controller.target.y);
+        let expected_yaw = 80.0f32.mul_add(-controller.rotate_sensitivity, TAU);
+        assert!((controller.yaw - expected_yaw).abs() < 1e-5);
+    }
+
+    #[test]
+    fn drag_direction_is_symmetric() {
+        let mut right = OrbitController::new(Vec3::ZERO, 3.0);
+        let mut left = right;
+        let press = button(MouseButton::Left, true);
+        feed(
+            &mut right,
+            &[cursor(100.0, 100.0), press, cursor(130.0, 100.0)],
+        );
+        feed(
+            &mut left,
+            &[cursor(100.0, 100.0), press, cursor(70.0, 100.0)],
+        );
+        let (right, left) = (right.position(), left.position());
+        assert!((right.x + left.x).abs() < 1e-5);
+        assert!((right.z - left.z).abs() < 1e-5);
+    }
+
+    #[test]
+    fn pitch_is_clamped() {
+        let mut controller = OrbitController::new(Vec3::ZERO, 3.0);
+        feed(
+            &mut controller,
+            &[
+                cursor(0.0, 0.0),
+                button(MouseButton::Left, true),
+                cursor(0.0, 10_000.0),
+            ],
+        );
+        assert!((controller.pitch + MAX_PITCH).abs() < 1e-6);
+        let mut camera = camera();
+        controller.update_camera(&mut camera);
+        assert!(camera.forward().y > 0.99);
+    }
+
+    #[test]
+    fn right_drag_pans_the_target() {
+        let mut controller = OrbitController::new(Vec3::ZERO, 3.0);
+        feed(
+            &mut controller,
+            &[
+                cursor(0.0, 0.0),
+                button(MouseButton::Right, true),
+                cursor(50.0, 0.0),
+            ],
+        );
+        assert!(controller.target.x < 0.0);
+        assert!(controller.target.y.abs() < 1e-6);
+        assert!((controller.position() - controller.target).length() - 3.0 < 1e-5);
+    }
+
+    #[test]
+    fn scroll_and_keys_zoom_within_limits() {
+        let mut controller = OrbitController::new(Vec3::ZERO, 3.0);
+        controller.handle_input(&Input::Scroll { lines: 1.0 });
+        assert!((controller.distance - 2.7).abs() < 1e-5);
+        controller.handle_input(&Input::Key {
+            key: VirtualKeyCode::Minus,
+            pressed: true,
+        });
+        assert!((controller.distance - 3.0).abs() < 1e-5);
+        feed(&mut controller, &[Input::Scroll { lines: 1000.0 }]);
+        assert!((controller.distance - controller.min_distance).abs() < 1e-6);
+        feed(&mut controller, &[Input::Scroll { lines: -1000.0 }]);
+        assert!((controller.distance - controller.max_distance).abs() < 1e-3);
+    }
+
+    #[test]
+    fn losing_focus_ends_the_drag() {
+        let mut controller = OrbitController::new(Vec3::ZERO, 3.0);
+        feed(
+            &mut controller,
+            &[
+                cursor(0.0, 0.0),
+                button(MouseButton::Left, true),
+                Input::Focused(false),
+            ],
+        );
+        let before = controller.position();
+        feed(&mut controller, &[cursor(10.0, 0.0), cursor(90.0, 30.0)]);
+        assert_eq!(controller.position(), before);
+    }
+
+    #[test]
+    fn updated_camera_looks_at_the_target() {
+        let mut controller = OrbitController::new(Vec3::new(1.0, 0.5, 0.8), 2.8);
+        feed(
+            &mut controller,
+            &[
+                cursor(0.0, 0.0),
+                button(MouseButton::Left, true),
+                cursor(123.0, -45.0),
+            ],
+        );
+        let mut camera = camera();
+        controller.update_camera(&mut camera);
+        let in_view = camera.view_matrix().transform_point(controller.target);
+        assert!(in_view.x.abs() < 1e-4 && in_view.y.abs() < 1e-4);
+        assert!((in_view.z - 2.8).abs() < 1e-4);
+    }
+}
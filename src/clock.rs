@@ -0,0 +1,301 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// frames longer than this (breakpoints, window drags) are clamped so the simulation
+// doesn't try to catch up with hundreds of fixed steps at once
+pub const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+// number of frames `FrameStats` averages over
+const STATS_WINDOW: usize = 120;
+
+// measures the real time between frames and turns it into simulation time:
+// scaled, stopped while paused and cut into fixed steps for deterministic updates
+#[derive(Clone, Debug)]
+pub struct Clock {
+    // simulation seconds per real second
+    pub time_scale: f32,
+    fixed_step: Duration,
+    last_tick: Option<Instant>,
+    paused: bool,
+    accumulator: Duration,
+    elapsed: Duration,
+    stats: FrameStats,
+}
+
+// what happened since the previous `Clock::tick`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frame {
+    // wall clock time, unscaled and also measured while paused, for what keeps going while the
+    // simulation stops, like camera movement (clamped to `MAX_FRAME_TIME`)
+    pub real_delta: Duration,
+    // simulation time, for variable-step updates of the animation
+    pub delta: Duration,
+    // fixed steps of `Clock::fixed_step` to simulate this frame
+    pub fixed_steps: u32,
+    // how far between the last two fixed steps this frame is, in [0, 1), to interpolate rendering
+    pub alpha: f32,
+}
+
+impl Clock {
+    #[must_use]
+    pub const fn new(fixed_step: Duration) -> Self {
+        Self {
+            time_scale: 1.0,
+            fixed_step,
+            last_tick: None,
+            paused: false,
+            accumulator: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            stats: FrameStats::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn fixed_step(&self) -> Duration {
+        self.fixed_step
+    }
+
+    // total simulation time
+    #[must_use]
+    pub const fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    #[must_use]
+    pub const fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    #[must_use]
+    pub const fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub const fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub const fn toggle_paused(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    // forgets the previous tick, so the next one reports no time passed;
+    // for when frames stopped for a while on purpose (minimized, unfocused)
+    pub const fn restart(&mut self) {
+        self.last_tick = None;
+    }
+
+    // call once per rendered frame
+    pub fn tick(&mut self) -> Frame {
+        self.tick_at(Instant::now())
+    }
+
+    // `tick` with an explicit time, the first call only starts the clock
+    pub fn tick_at(&mut self, now: Instant) -> Frame {
+        let real_delta = self
+            .last_tick
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        self.last_tick = Some(now);
+        if real_delta > Duration::ZERO {
+            self.stats.push(real_delta);
+        }
+
+        let delta = if self.paused {
+            Duration::ZERO
+        } else {
+            real_delta
+                .min(MAX_FRAME_TIME)
+                .mul_f32(self.time_scale.max(0.0))
+        };
+        self.elapsed += delta;
+        self.accumulator += delta;
+
+        let mut fixed_steps = 0;
+        while self.accumulator >= self.fixed_step {
+            self.accumulator -= self.fixed_step;
+            fixed_steps += 1;
+        }
+
+        Frame {
+            real_delta,
+            delta,
+            fixed_steps,
+            alpha: self.accumulator.as_secs_f32() / self.fixed_step.as_secs_f32(),
+        }
+    }
+}
+
+// frame times over the last `STATS_WINDOW` frames
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    frame_times: VecDeque<Duration>,
+}
+
+impl FrameStats {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            frame_times: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == STATS_WINDOW {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    #[must_use]
+    pub fn min(&self) -> Duration {
+        self.frame_times.iter().min().copied().unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn max(&self) -> Duration {
+        self.frame_times.iter().max().copied().unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn avg(&self) -> Duration {
+        let count = u32::try_from(self.frame_times.len()).unwrap_or(u32::MAX);
+        if count == 0 {
+            return Duration::ZERO;
+        }
+        self.frame_times.iter().sum::<Duration>() / count
+    }
+
+    #[must_use]
+    pub fn fps(&self) -> f32 {
+        let avg = self.avg().as_secs_f32();
+        if avg > 0.0 {
+            1.0 / avg
+        } else {
+            0.0
+        }
+    }
+}
+
+impl std::fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ms = |d: Duration| d.as_secs_f32() * 1000.0;
+        write!(
+            f,
+            "{:.0} fps, frame time {:.2} / {:.2} / {:.2} ms (min / avg / max)",
+            self.fps(),
+            ms(self.min()),
+            ms(self.avg()),
+            ms(self.max())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    // a clock that has been started at `start`
+    fn started(start: Instant) -> Clock {
+        let mut clock = Clock::new(STEP);
+        clock.tick_at(start);
+        clock
+    }
+
+    #[test]
+    fn first_tick_only_starts_the_clock() {
+        let mut clock = Clock::new(STEP);
+        let frame = clock.tick_at(Instant::now());
+        assert_eq!(frame.real_delta, Duration::ZERO);
+        assert_eq!(frame.fixed_steps, 0);
+        assert_eq!(clock.stats().avg(), Duration::ZERO);
+    }
+
+    #[test]
+    fn delta_is_measured_not_assumed() {
+        let start = Instant::now();
+        let mut clock = started(start);
+        let frame = clock.tick_at(start + Duration::from_millis(33));
+        assert_eq!(frame.real_delta, Duration::from_millis(33));
+        assert_eq!(frame.delta, Duration::from_millis(33));
+        assert_eq!(frame.fixed_steps, 3);
+        assert!((frame.alpha - 0.3).abs() < 1e-4);
+    }
+
+    #[test]
+    fn fixed_steps_add_up_regardless_of_frame_rate() {
+        let start = Instant::now();
+        for frame_ms in [1, 7, 16, 33, 50] {
+            let mut clock = started(start);
+            let mut steps = 0;
+            let mut now = start;
+            while now < start + Duration::from_secs(1) {
+                now += Duration::from_millis(frame_ms);
+                steps += clock.tick_at(now).fixed_steps;
+            }
+            let simulated = STEP * steps + STEP.mul_f32(clock.tick_at(now).alpha);
+            assert!(
+                (simulated.as_secs_f32() - clock.elapsed().as_secs_f32()).abs() < 1e-3,
+                "{frame_ms} ms frames"
+            );
+        }
+    }
+
+    #[test]
+    fn paused_clock_measures_but_does_not_advance() {
+        let start = Instant::now();
+        let mut clock = started(start);
+        clock.toggle_paused();
+        let frame = clock.tick_at(start + Duration::from_millis(20));
+        assert_eq!(frame.real_delta, Duration::from_millis(20));
+        assert_eq!(frame.delta, Duration::ZERO);
+        assert_eq!(frame.fixed_steps, 0);
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+
+        clock.toggle_paused();
+        let frame = clock.tick_at(start + Duration::from_millis(40));
+        assert_eq!(frame.delta, Duration::from_millis(20));
+    }
+
+    #[test]
+    fn time_scale_scales_simulation_time() {
+        let start = Instant::now();
+        let mut clock = started(start);
+        clock.time_scale = 0.5;
+        let frame = clock.tick_at(start + Duration::from_millis(40));
+        assert_eq!(frame.real_delta, Duration::from_millis(40));
+        assert_eq!(frame.delta, Duration::from_millis(20));
+        assert_eq!(frame.fixed_steps, 2);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let start = Instant::now();
+        let mut clock = started(start);
+        let frame = clock.tick_at(start + Duration::from_secs(5));
+        assert_eq!(frame.delta, MAX_FRAME_TIME);
+        assert_eq!(frame.fixed_steps, 25);
+    }
+
+    #[test]
+    fn stats_cover_the_recent_window() {
+        let start = Instant::now();
+        let mut clock = started(start);
+        let mut now = start;
+        for ms in [10, 20, 30] {
+            now += Duration::from_millis(ms);
+            clock.tick_at(now);
+        }
+        assert_eq!(clock.stats().min(), Duration::from_millis(10));
+        assert_eq!(clock.stats().max(), Duration::from_millis(30));
+        assert_eq!(clock.stats().avg(), Duration::from_millis(20));
+        assert!((clock.stats().fps() - 50.0).abs() < 1e-3);
+
+        for _ in 0..STATS_WINDOW {
+            now += Duration::from_millis(5);
+            clock.tick_at(now);
+        }
+        assert_eq!(clock.stats().max(), Duration::from_millis(5));
+    }
+}
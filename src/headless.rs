@@ -0,0 +1,91 @@
+mod egl;
+
+use crate::image::Image;
+use glium::backend::{Context, Facade};
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::texture::{DepthFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use std::fmt;
+use std::rc::Rc;
+
+// an OpenGL context with no window and no display server behind it, rendering into textures;
+// it goes through Mesa's EGL surfaceless platform, which falls back to the llvmpipe software
+// rasterizer on machines without a GPU
+pub struct Headless {
+    context: Rc<Context>,
+}
+
+impl Headless {
+    /// # Errors
+    /// If libEGL is missing, has no surfaceless platform or can't create an OpenGL context.
+    pub fn new() -> Result<Self, HeadlessError> {
+        let backend = egl::SurfacelessBackend::new()?;
+        // SAFETY: the context was just created and nothing else has touched its state
+        let context = unsafe {
+            Context::new(
+                backend,
+                true,
+                glium::debug::DebugCallbackBehavior::DebugMessageOnError,
+            )
+        }
+        .map_err(HeadlessError::IncompatibleOpenGl)?;
+        Ok(Self { context })
+    }
+
+    /// Runs `draw` on a `width` by `height` framebuffer with a 24 bit depth buffer and reads the
+    /// color back.
+    ///
+    /// # Panics
+    /// If the framebuffer can't be created, e.g. for a zero or too large size.
+    pub fn render<F>(&self, width: u32, height: u32, draw: F) -> Image
+    where
+        F: FnOnce(&mut SimpleFrameBuffer),
+    {
+        let color = Texture2d::empty_with_format(
+            self,
+            UncompressedFloatFormat::U8U8U8U8,
+            MipmapsOption::NoMipmap,
+            width,
+            height,
+        )
+        .unwrap();
+        let depth = DepthRenderBuffer::new(self, DepthFormat::I24, width, height).unwrap();
+        let mut target = SimpleFrameBuffer::with_depth_buffer(self, &color, &depth).unwrap();
+
+        draw(&mut target);
+
+        let pixels: RawImage2d<u8> = color.read();
+        Image::from_bottom_up(pixels.width, pixels.height, &pixels.data)
+    }
+}
+
+impl Facade for Headless {
+    fn get_context(&self) -> &Rc<Context> {
+        &self.context
+    }
+}
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    // libEGL couldn't be loaded
+    Library(libloading::Error),
+    // the EGL implementation has no surfaceless platform (only Mesa's does)
+    NoSurfacelessPlatform,
+    // an EGL call failed, with the name of the call and the `eglGetError` code
+    Egl(&'static str, i32),
+    IncompatibleOpenGl(glium::IncompatibleOpenGl),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Library(error) => write!(f, "couldn't load libEGL: {error}"),
+            Self::NoSurfacelessPlatform => {
+                write!(f, "EGL doesn't support EGL_MESA_platform_surfaceless")
+            }
+            Self::Egl(call, code) => write!(f, "{call} failed with EGL error {code:#x}"),
+            Self::IncompatibleOpenGl(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
@@ -0,0 +1,166 @@
+// EGL's enum values all fit in the EGLint attribute lists they're passed in
+#![allow(clippy::cast_possible_wrap)]
+
+use super::HeadlessError;
+use glium::backend::Backend;
+use glium::SwapBuffersError;
+use glutin_egl_sys::egl;
+use glutin_egl_sys::egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLenum, EGLint};
+use libloading::Library;
+use std::ffi::{c_void, CStr, CString};
+use std::ptr;
+
+// from EGL_MESA_platform_surfaceless, which the generated bindings don't include
+const PLATFORM_SURFACELESS_MESA: EGLenum = 0x31DD;
+
+// an EGL context made current without any surface, everything is drawn into framebuffer objects
+pub struct SurfacelessBackend {
+    egl: egl::Egl,
+    display: EGLDisplay,
+    context: EGLContext,
+    // the function pointers in `egl` point into the library, so it goes last
+    _library: Library,
+}
+
+impl SurfacelessBackend {
+    pub fn new() -> Result<Self, HeadlessError> {
+        // SAFETY: libEGL has no initialisation routines with preconditions
+        let library = unsafe { Library::new("libEGL.so.1") }
+            .or_else(|_| unsafe { Library::new("libEGL.so") })
+            .map_err(HeadlessError::Library)?;
+        let get_proc_address = unsafe {
+            library.get::<unsafe extern "C" fn(*const i8) -> *const c_void>(b"eglGetProcAddress\0")
+        }
+        .map_err(HeadlessError::Library)?;
+        let egl = egl::Egl::load_with(|symbol| {
+            let name = CString::new(symbol).unwrap();
+            // SAFETY: only function pointers are looked up, they're cast to the right
+            // signatures by the bindings; before EGL 1.5 eglGetProcAddress only knew extensions
+            unsafe {
+                library
+                    .get::<*const c_void>(name.as_bytes_with_nul())
+                    .map_or_else(|_| get_proc_address(name.as_ptr()), |symbol| *symbol)
+            }
+        });
+
+        // SAFETY: every call gets valid handles and NONE-terminated attribute lists, and the
+        // results are checked before they're used
+        unsafe {
+            let client_extensions = egl.QueryString(egl::NO_DISPLAY, egl::EXTENSIONS as EGLint);
+            let supported = !client_extensions.is_null()
+                && CStr::from_ptr(client_extensions)
+                    .to_string_lossy()
+                    .split(' ')
+                    .any(|extension| extension == "EGL_MESA_platform_surfaceless");
+            if !supported || !egl.GetPlatformDisplayEXT.is_loaded() {
+                return Err(HeadlessError::NoSurfacelessPlatform);
+            }
+
+            let display = egl.GetPlatformDisplayEXT(
+                PLATFORM_SURFACELESS_MESA,
+                egl::DEFAULT_DISPLAY.cast_mut(),
+                ptr::null(),
+            );
+            if display == egl::NO_DISPLAY {
+                return Err(error(&egl, "eglGetPlatformDisplayEXT"));
+            }
+            let (mut major, mut minor) = (0, 0);
+            if egl.Initialize(display, &raw mut major, &raw mut minor) == egl::FALSE {
+                return Err(error(&egl, "eglInitialize"));
+            }
+            if egl.BindAPI(egl::OPENGL_API) == egl::FALSE {
+                return Err(error(&egl, "eglBindAPI"));
+            }
+
+            // no surface type is asked for, the surfaceless platform only has pbuffer configs
+            let config_attributes = [
+                egl::RENDERABLE_TYPE as EGLint,
+                egl::OPENGL_BIT as EGLint,
+                egl::SURFACE_TYPE as EGLint,
+                0,
+                egl::NONE as EGLint,
+            ];
+            let mut config: EGLConfig = ptr::null();
+            let mut config_count = 0;
+            if egl.ChooseConfig(
+                display,
+                config_attributes.as_ptr(),
+                &raw mut config,
+                1,
+                &raw mut config_count,
+            ) == egl::FALSE
+                || config_count == 0
+            {
+                return Err(error(&egl, "eglChooseConfig"));
+            }
+
+            let context_attributes = [egl::NONE as EGLint];
+            let context = egl.CreateContext(
+                display,
+                config,
+                egl::NO_CONTEXT,
+                context_attributes.as_ptr(),
+            );
+            if context == egl::NO_CONTEXT {
+                return Err(error(&egl, "eglCreateContext"));
+            }
+
+            Ok(Self {
+                egl,
+                display,
+                context,
+                _library: library,
+            })
+        }
+    }
+}
+
+unsafe fn error(egl: &egl::Egl, call: &'static str) -> HeadlessError {
+    HeadlessError::Egl(call, egl.GetError())
+}
+
+// SAFETY: the context is only ever made current on its own, without surfaces, and
+// `get_proc_address` gets its symbols from the EGL library that created it
+unsafe impl Backend for SurfacelessBackend {
+    fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+        // nothing to present
+        Ok(())
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        let name = CString::new(symbol).unwrap();
+        self.egl.GetProcAddress(name.as_ptr()).cast()
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        // there's no default framebuffer
+        (0, 0)
+    }
+
+    fn is_current(&self) -> bool {
+        // SAFETY: no preconditions
+        unsafe { self.egl.GetCurrentContext() == self.context }
+    }
+
+    unsafe fn make_current(&self) {
+        self.egl
+            .MakeCurrent(self.display, egl::NO_SURFACE, egl::NO_SURFACE, self.context);
+    }
+}
+
+impl Drop for SurfacelessBackend {
+    fn drop(&mut self) {
+        // SAFETY: the handles came from this EGL instance and aren't used afterwards
+        unsafe {
+            if self.is_current() {
+                self.egl.MakeCurrent(
+                    self.display,
+                    egl::NO_SURFACE,
+                    egl::NO_SURFACE,
+                    egl::NO_CONTEXT,
+                );
+            }
+            self.egl.DestroyContext(self.display, self.context);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::Path;
+
+// 8 bit RGBA pixels, rows from the top down like image files (OpenGL reads back bottom up)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// # Panics
+    /// If `pixels` doesn't hold `width * height` RGBA pixels.
+    #[must_use]
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), width as usize * height as usize * 4);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // image from rows stored bottom up, as `glReadPixels` and texture reads return them
+    #[must_use]
+    pub fn from_bottom_up(width: u32, height: u32, pixels: &[u8]) -> Self {
+        let row_len = width as usize * 4;
+        let pixels = pixels
+            .chunks_exact(row_len)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        Self::new(width, height, pixels)
+    }
+
+    #[must_use]
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let start = (y as usize * self.width as usize + x as usize) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.pixels[start..start + 4]);
+        pixel
+    }
+
+    /// # Errors
+    /// If the file can't be created or written.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Reads an 8 bit RGB or RGBA PNG, RGB gets an opaque alpha channel.
+    ///
+    /// # Errors
+    /// If the file can't be read, isn't a PNG or has another pixel format.
+    pub fn load_png(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_png(BufReader::new(File::open(path)?))
+    }
+
+    /// [`Image::load_png`] for PNG data from anywhere else, such as a glTF buffer.
+    ///
+    /// # Errors
+    /// If the data can't be read, isn't a PNG or has another pixel format.
+    pub fn read_png(data: impl Read) -> io::Result<Self> {
+        let decoder = png::Decoder::new(data);
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match (info.color_type, info.bit_depth) {
+            (png::ColorType::Rgba, png::BitDepth::Eight) => buffer,
+            (png::ColorType::Rgb, png::BitDepth::Eight) => buffer
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], u8::MAX])
+                .collect(),
+            (color_type, bit_depth) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported PNG format {color_type:?} {bit_depth:?}"),
+                ))
+            }
+        };
+        Ok(Self::new(info.width, info.height, pixels))
+    }
+}
+
+// result of `compare`
+pub struct Difference {
+    // pixels whose color difference is over the threshold
+    pub mismatched: usize,
+    // the expected image faded to gray, with mismatched pixels in red
+    pub diff: Image,
+}
+
+impl Difference {
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mismatched_fraction(&self) -> f32 {
+        let pixel_count = self.diff.width as usize * self.diff.height as usize;
+        self.mismatched as f32 / pixel_count as f32
+    }
+}
+
+/// Compares two images pixel by pixel, counting the pixels whose colors differ by more than
+/// `threshold`.
+///
+/// The difference is measured in YIQ space like pixelmatch does, weighting brightness over hue,
+/// and scaled so that 0 only accepts exact matches and 1 accepts anything. Alpha is ignored, both
+/// images are expected to be opaque.
+///
+/// # Panics
+/// If the images don't have the same size.
+#[must_use]
+pub fn compare(expected: &Image, actual: &Image, threshold: f32) -> Difference {
+    assert_eq!(
+        (expected.width, expected.height),
+        (actual.width, actual.height),
+        "images of different sizes"
+    );
+
+    let max_delta = MAX_YIQ_DELTA * threshold * threshold;
+    let mut mismatched = 0;
+    let mut diff = Vec::with_capacity(expected.pixels.len());
+    for (a, b) in expected
+        .pixels
+        .chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+    {
+        if yiq_delta(a, b) > max_delta {
+            mismatched += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            // faded brightness of the expected pixel, for context
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let gray = yiq(a).0.mul_add(0.1, 0.9 * 255.0) as u8;
+            diff.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+
+    Difference {
+        mismatched,
+        diff: Image::new(expected.width, expected.height, diff),
+    }
+}
+
+// the delta between black and white
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+// brightness, orange-blue and purple-green components of an RGB color
+fn yiq(pixel: &[u8]) -> (f32, f32, f32) {
+    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(f32::from);
+    yiq_from_rgb(r, g, b)
+}
+
+fn yiq_from_rgb(red: f32, green: f32, blue: f32) -> (f32, f32, f32) {
+    let y = red.mul_add(0.298_895_3, green.mul_add(0.586_622_5, blue * 0.114_482_23));
+    let i = red.mul_add(
+        0.595_977_99,
+        green.mul_add(-0.274_176_1, blue * -0.321_801_9),
+    );
+    let q = red.mul_add(
+        0.211_470_17,
+        green.mul_add(-0.522_617_1, blue * 0.311_146_94),
+    );
+    (y, i, q)
+}
+
+fn yiq_delta(expected: &[u8], actual: &[u8]) -> f32 {
+    let [red, green, blue] = [0, 1, 2].map(|c| f32::from(expected[c]) - f32::from(actual[c]));
+    let (y, i, q) = yiq_from_rgb(red, green, blue);
+    (0.5053 * y).mul_add(y, (0.299 * i).mul_add(i, 0.1957 * q * q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bottom_up_rows_are_flipped() {
+        let bottom_up = [1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4];
+        let image = Image::from_bottom_up(2, 2, &bottom_up);
+        assert_eq!(image.pixel(0, 0), [3; 4]);
+        assert_eq!(image.pixel(1, 0), [4; 4]);
+        assert_eq!(image.pixel(0, 1), [1; 4]);
+    }
+
+    #[test]
+    fn compare_uses_the_threshold() {
+        let black = Image::new(1, 2, vec![0, 0, 0, 255, 0, 0, 0, 255]);
+        let white = Image::new(1, 2, vec![255; 8]);
+        let nearly_black = Image::new(1, 2, vec![0, 0, 0, 255, 8, 8, 8, 255]);
+
+        assert_eq!(compare(&black, &black, 0.0).mismatched, 0);
+        assert_eq!(compare(&black, &white, 0.9).mismatched, 2);
+        assert_eq!(compare(&black, &nearly_black, 0.1).mismatched, 0);
+        assert_eq!(compare(&black, &nearly_black, 0.01).mismatched, 1);
+        assert_eq!(
+            compare(&black, &nearly_black, 0.01).diff.pixel(0, 1),
+            [255, 0, 0, 255]
+        );
+    }
+}
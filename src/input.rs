@@ -0,0 +1,75 @@
+use glium::glutin::event::{
+    DeviceEvent, ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode,
+    WindowEvent,
+};
+
+// pixels of a `MouseScrollDelta::PixelDelta` (touchpads) treated as one wheel line
+const PIXELS_PER_SCROLL_LINE: f64 = 40.0;
+
+// the subset of glutin's window and device events camera controllers care about.
+// Controllers only ever see `Input`, so tests can drive them with synthetic sequences
+// without opening a window (glutin events carry a `DeviceId` that can't be made up safely).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Input {
+    MouseButton { button: MouseButton, pressed: bool },
+    // window coordinates in physical pixels, top-left origin
+    CursorMoved { x: f64, y: f64 },
+    // the cursor left the window, any drag in progress ends
+    CursorLeft,
+    // positive is away from the user
+    Scroll { lines: f32 },
+    Key { key: VirtualKeyCode, pressed: bool },
+    // raw, unaccelerated mouse movement, also reported while the cursor is grabbed
+    MouseMotion { dx: f64, dy: f64 },
+    // losing focus releases every held key and button
+    Focused(bool),
+}
+
+impl Input {
+    #[must_use]
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match *event {
+            WindowEvent::MouseInput { state, button, .. } => Some(Self::MouseButton {
+                button,
+                pressed: state == ElementState::Pressed,
+            }),
+            WindowEvent::CursorMoved { position, .. } => Some(Self::CursorMoved {
+                x: position.x,
+                y: position.y,
+            }),
+            WindowEvent::CursorLeft { .. } => Some(Self::CursorLeft),
+            WindowEvent::MouseWheel { delta, .. } => {
+                #[allow(clippy::cast_possible_truncation)]
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        (position.y / PIXELS_PER_SCROLL_LINE) as f32
+                    }
+                };
+                Some(Self::Scroll { lines })
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => Some(Self::Key {
+                key,
+                pressed: state == ElementState::Pressed,
+            }),
+            WindowEvent::Focused(focused) => Some(Self::Focused(focused)),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn from_device_event(event: &DeviceEvent) -> Option<Self> {
+        match *event {
+            DeviceEvent::MouseMotion { delta: (dx, dy) } => Some(Self::MouseMotion { dx, dy }),
+            _ => None,
+        }
+    }
+}
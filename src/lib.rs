@@ -0,0 +1,20 @@
+#![warn(clippy::all, clippy::pedantic, clippy::nursery)]
+
+#[macro_use]
+extern crate glium;
+
+pub mod app;
+pub mod camera;
+pub mod clock;
+#[cfg(target_os = "linux")]
+pub mod headless;
+pub mod image;
+pub mod input;
+pub mod matrices;
+pub mod mesh;
+pub mod scene;
+pub mod shaders;
+pub mod software;
+// pub mod shapes;
+pub mod teapot;
+pub mod transform;
@@ -1,136 +1,363 @@
 #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
 
-// mod shapes;
-mod matrices;
-mod shaders;
-mod teapot;
-
-#[macro_use]
-extern crate glium;
-
+use glium::glutin::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
 use glium::{glutin, Surface};
+use learn_opengl_with_glium::app::{self, App, Context};
+use learn_opengl_with_glium::camera::fly::FlyController;
+use learn_opengl_with_glium::camera::orbit::OrbitController;
+use learn_opengl_with_glium::camera::Camera;
+use learn_opengl_with_glium::clock::{self, Frame};
+use learn_opengl_with_glium::input::Input;
+use learn_opengl_with_glium::matrices::{Projection, Vec3};
+use learn_opengl_with_glium::mesh::normals::Weighting;
+use learn_opengl_with_glium::mesh::{gltf, obj, Mesh};
+use learn_opengl_with_glium::scene::{fit_to_teapot, TeapotScene, TEAPOT_CENTER};
+use learn_opengl_with_glium::software::{self, Framebuffer};
 use std::f32::consts::TAU;
 
 fn main() {
-    // init Display
-    let event_loop = glutin::event_loop::EventLoop::new();
-    let window_builder = glutin::window::WindowBuilder::new()
-        // .with_resizable(false)
-        .with_inner_size(glutin::dpi::LogicalSize::new(600, 600))
-        .with_always_on_top(true)
-        // .with_decorations(false)
-        .with_position(glutin::dpi::LogicalPosition::new(53.0, 420.0));
-    let context_builder = glutin::ContextBuilder::new().with_depth_buffer(24);
-    let display = glium::Display::new(window_builder, context_builder, &event_loop).unwrap();
-
-    let teapot_positions = glium::VertexBuffer::new(&display, &teapot::VERTICES).unwrap();
-    let teapot_normals = glium::VertexBuffer::new(&display, &teapot::NORMALS).unwrap();
-    let teapot_indices = glium::IndexBuffer::new(
-        &display,
-        glium::index::PrimitiveType::TrianglesList,
-        &teapot::INDICES,
-    )
-    .unwrap();
-    let mut rotation: f32 = 0.0;
-
-    // shaders
-    let vertex_shader_src: &str = shaders::VERTEX_SHADER;
-    let fragment_shader_src: &str = shaders::FRAGMENT_SHADER;
-
-    // program
-    let program =
-        glium::Program::from_source(&display, vertex_shader_src, fragment_shader_src, None)
-            .unwrap();
-
-    // event loop (game loop?)
-    event_loop.run(move |event, _, control_flow| {
-        #[allow(clippy::collapsible_match, clippy::match_same_arms)]
-        match event {
-            glutin::event::Event::WindowEvent { event, .. } => match event {
-                glutin::event::WindowEvent::CloseRequested => {
-                    *control_flow = glutin::event_loop::ControlFlow::Exit;
-                    return;
-                }
-                _ => return,
-            },
-            glutin::event::Event::NewEvents(cause) => match cause {
-                glutin::event::StartCause::ResumeTimeReached { .. } => (),
-                glutin::event::StartCause::Init => (),
-                _ => return,
+    // `--model file.obj` (or .gltf/.glb) shows a model in place of the teapot, `--flip-winding`
+    // turns an OBJ model inside out for files whose front faces are counterclockwise (the
+    // teapot's are clockwise, OBJ doesn't say; glTF's are always counterclockwise and flipped
+    // when loading),
+    // `--headless [path]` renders one frame to a PNG without opening a window,
+    // `--software [path]` does the same without OpenGL at all,
+    // `--optimize` reorders the triangles and vertices for the GPU and prints how much it helped
+    let mut output = Output::Window;
+    let mut model = None;
+    let mut flip_winding = false;
+    let mut optimize = false;
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        let mut path = || {
+            args.next_if(|next| !next.starts_with("--"))
+                .unwrap_or_else(|| "teapot.png".to_owned())
+        };
+        match arg.as_str() {
+            "--headless" => output = Output::Headless(path()),
+            "--software" => output = Output::Software(path()),
+            "--flip-winding" => flip_winding = true,
+            "--optimize" => optimize = true,
+            "--model" => match args.next() {
+                Some(path) => model = Some(path),
+                None => exit_with("--model needs a file"),
             },
-            _ => return,
+            _ => exit_with(&format!("unknown argument {arg}")),
+        }
+    }
+
+    if flip_winding && model.as_deref().is_some_and(is_gltf) {
+        exit_with("--flip-winding is for OBJ models, glTF's winding is always counterclockwise");
+    }
+    let mut mesh = model.as_deref().map_or_else(
+        || {
+            let mut teapot = Mesh::teapot();
+            if flip_winding {
+                teapot.flip_winding();
+            }
+            teapot
+        },
+        |path| load_model(path, flip_winding),
+    );
+    if optimize {
+        println!("vertex cache: {}", mesh.optimize());
+    }
+
+    match output {
+        Output::Window => {
+            // init Display
+            let window_builder = glutin::window::WindowBuilder::new()
+                // .with_resizable(false)
+                .with_inner_size(glutin::dpi::LogicalSize::new(600, 600))
+                .with_always_on_top(true)
+                // .with_decorations(false)
+                .with_position(glutin::dpi::LogicalPosition::new(53.0, 420.0));
+
+            app::run("learn_opengl_with_glium", window_builder, move |display| {
+                TeapotApp::new(display, &mesh)
+            });
+        }
+        Output::Headless(path) => render_headless(&path, &mesh),
+        Output::Software(path) => render_software(&path, &mesh),
+    }
+}
+
+enum Output {
+    Window,
+    Headless(String),
+    Software(String),
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1);
+}
+
+fn is_gltf(path: &str) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .is_some_and(|extension| matches!(extension.to_ascii_lowercase().as_str(), "gltf" | "glb"))
+}
+
+// in the teapot's clockwise winding, `counterclockwise` for OBJ files whose front faces aren't
+fn load_model(path: &str, counterclockwise: bool) -> Mesh {
+    let mesh = if is_gltf(path) {
+        gltf::load(path)
+            .map(|scene| scene.flatten())
+            .map_err(|error| error.to_string())
+    } else {
+        obj::load(path)
+            .map(|model| model.mesh)
+            .map_err(|error| error.to_string())
+    };
+    let mut mesh =
+        mesh.unwrap_or_else(|error| exit_with(&format!("couldn't load {path}: {error}")));
+    // normals and tangents are generated for counterclockwise triangles, the mesh is turned
+    // that way for them and into the teapot's winding afterwards
+    if !counterclockwise && !is_gltf(path) {
+        mesh.flip_winding();
+    }
+    if mesh.normals.is_empty() {
+        // smooth, apart from edges sharper than 60 degrees
+        mesh.generate_normals(Weighting::Angle, TAU / 6.0);
+    }
+    if !mesh.texcoords.is_empty() && mesh.tangents.is_empty() {
+        if let Err(error) = mesh.generate_tangents() {
+            eprintln!("{path}: {error}");
+        }
+    }
+    mesh.flip_winding();
+    fit_to_teapot(&mut mesh);
+    mesh
+}
+
+// P cycles projections, F switches between orbiting the teapot and flying,
+// K pauses the animation, [ and ] halve and double its speed
+struct TeapotApp {
+    scene: TeapotScene,
+
+    rotation: f32,
+    previous_rotation: f32,
+    drawn_rotation: f32,
+
+    projections: [Projection; 4],
+    projection_index: usize,
+    camera: Camera,
+    controls: Controls,
+    cursor_grabbed: bool,
+}
+
+impl TeapotApp {
+    fn new(display: &glium::Display, mesh: &Mesh) -> Self {
+        let projections = projections();
+        let camera = Camera::new(Vec3::new(0.0, 0.0, -2.0), projections[0]);
+        let controls = Controls::Orbit(OrbitController::around(&camera, TEAPOT_CENTER));
+
+        Self {
+            scene: TeapotScene::with_mesh(display, mesh),
+            rotation: 0.0,
+            previous_rotation: 0.0,
+            drawn_rotation: 0.0,
+            projections,
+            projection_index: 0,
+            camera,
+            controls,
+            cursor_grabbed: false,
+        }
+    }
+
+    fn handle_key(&mut self, context: &mut Context, key: VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::P => {
+                self.projection_index = (self.projection_index + 1) % self.projections.len();
+                self.camera.projection = self.projections[self.projection_index];
+            }
+            VirtualKeyCode::F => {
+                self.controls = match self.controls {
+                    Controls::Orbit(_) => Controls::Fly(FlyController::from_camera(&self.camera)),
+                    Controls::Fly(_) => {
+                        Controls::Orbit(OrbitController::around(&self.camera, TEAPOT_CENTER))
+                    }
+                };
+            }
+            VirtualKeyCode::K => context.clock.toggle_paused(),
+            VirtualKeyCode::LBracket => context.clock.time_scale /= 2.0,
+            VirtualKeyCode::RBracket => context.clock.time_scale *= 2.0,
+            _ => (),
+        }
+    }
+}
+
+impl App for TeapotApp {
+    fn on_event(&mut self, context: &mut Context, event: &Event<'_, ()>) {
+        let input = match event {
+            Event::WindowEvent { event, .. } => {
+                if let WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } = event
+                {
+                    self.handle_key(context, *key);
+                }
+                Input::from_window_event(event)
+            }
+            Event::DeviceEvent { event, .. } => Input::from_device_event(event),
+            _ => None,
+        };
+        if let Some(input) = input {
+            self.controls.handle_input(&input);
+        }
+
+        if self.controls.cursor_grabbed() != self.cursor_grabbed {
+            self.cursor_grabbed = self.controls.cursor_grabbed();
+            grab_cursor(&context.display, self.cursor_grabbed);
         }
-        // frame time        
-        let fps: u128 = 60;
-        #[allow(clippy::cast_possible_truncation)]
-        let nanos_between_frames: u64 = (std::time::Duration::from_secs(1).as_nanos() / fps) as u64;
-        let frame_time = std::time::Duration::from_nanos(nanos_between_frames);
-        let next_frame_time = std::time::Instant::now() + frame_time;
-        *control_flow = glutin::event_loop::ControlFlow::WaitUntil(next_frame_time);
+    }
+
+    fn update(&mut self, context: &mut Context, frame: &Frame) {
+        // camera movement keeps working while the animation is paused, and is clamped like the
+        // fixed steps so a stall doesn't throw the camera across the scene
+        self.controls.update_camera(
+            &mut self.camera,
+            frame.real_delta.min(clock::MAX_FRAME_TIME),
+        );
 
-        // clear screen with a nice blue color
-        let mut target = display.draw();
+        // rotation angle, advanced in fixed steps and interpolated between the last two for drawing
+        let rotation_per_sec = TAU / 10.0;
+        let rotation_per_step = rotation_per_sec * context.clock.fixed_step().as_secs_f32();
+        for _ in 0..frame.fixed_steps {
+            self.previous_rotation = self.rotation;
+            self.rotation += rotation_per_step;
+        }
+        if self.previous_rotation > TAU {
+            self.previous_rotation -= TAU;
+            self.rotation -= TAU;
+        }
+        self.drawn_rotation =
+            (self.rotation - self.previous_rotation).mul_add(frame.alpha, self.previous_rotation);
+    }
 
+    fn render(&mut self, _context: &Context, target: &mut glium::Frame) {
         // get current aspect ratio
         let (width, height) = target.get_dimensions();
-        #[allow(clippy::cast_precision_loss)]
-        let aspect_ratio = height as f32 / width as f32;
+        self.camera.set_viewport(width, height);
 
-        // rotation angle
-        let rotation_per_sec = TAU / 10.0;
-        let rotation_per_frame = rotation_per_sec * frame_time.as_secs_f32();
-        rotation += rotation_per_frame;
-        if rotation > TAU {
-            rotation -= TAU;
+        self.scene.draw(target, &self.camera, self.drawn_rotation);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn render_headless(path: &str, mesh: &Mesh) {
+    use learn_opengl_with_glium::headless::Headless;
+
+    let headless = Headless::new()
+        .unwrap_or_else(|error| exit_with(&format!("couldn't create a headless context: {error}")));
+    let scene = TeapotScene::with_mesh(&headless, mesh);
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, -2.0), projections()[0]);
+    camera.set_viewport(HEADLESS_SIZE, HEADLESS_SIZE);
+
+    let image = headless.render(HEADLESS_SIZE, HEADLESS_SIZE, |target| {
+        scene.draw(target, &camera, 0.0);
+    });
+    if let Err(error) = image.save_png(path) {
+        exit_with(&format!("couldn't write {path}: {error}"));
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn render_headless(_path: &str, _mesh: &Mesh) {
+    exit_with("headless rendering needs EGL's surfaceless platform, which is Linux only");
+}
+
+fn render_software(path: &str, mesh: &Mesh) {
+    let mut framebuffer = Framebuffer::new(HEADLESS_SIZE, HEADLESS_SIZE);
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, -2.0), projections()[0]);
+    camera.set_viewport(HEADLESS_SIZE, HEADLESS_SIZE);
+    software::draw_mesh(&mut framebuffer, &camera, 0.0, mesh);
+
+    if let Err(error) = framebuffer.to_image().save_png(path) {
+        exit_with(&format!("couldn't write {path}: {error}"));
+    }
+}
+
+// same as the window's default size
+const HEADLESS_SIZE: u32 = 600;
+
+const fn projections() -> [Projection; 4] {
+    [
+        Projection::Perspective {
+            fov_angle: TAU / 6.0,
+            z_near: 0.1,
+            z_far: 1024.0,
+        },
+        Projection::InfinitePerspective {
+            fov_angle: TAU / 6.0,
+            z_near: 0.1,
+        },
+        Projection::ReversedZ {
+            fov_angle: TAU / 6.0,
+            z_near: 0.1,
+            z_far: None,
+        },
+        Projection::Orthographic {
+            height: 1.5,
+            z_near: 0.1,
+            z_far: 1024.0,
+        },
+    ]
+}
+
+enum Controls {
+    Orbit(OrbitController),
+    Fly(FlyController),
+}
+
+impl Controls {
+    fn handle_input(&mut self, input: &Input) {
+        match self {
+            Self::Orbit(orbit) => {
+                orbit.handle_input(input);
+            }
+            Self::Fly(fly) => fly.handle_input(input),
+        }
+    }
+
+    fn update_camera(&self, camera: &mut Camera, frame_time: std::time::Duration) {
+        match self {
+            Self::Orbit(orbit) => orbit.update_camera(camera),
+            Self::Fly(fly) => fly.update_camera(camera, frame_time),
         }
+    }
 
-        // light vector (or position?)
-        let light: [f32; 3] = [-0.9, 1.0, -0.2];
-
-        // transforms
-        let view_matrix = matrices::view(&[0.0, 0.0, -2.0], &[0.0, 0.0, 2.0], &[0.0, 1.0, 0.0]);
-        let translation_matrix = matrices::translation(0.0, 0.0, 0.8);
-        let scale_matrix = matrices::scale(0.008);
-        let rotation_matrix = matrices::rotation(matrices::Axis::Z, rotation);
-
-        let model_view_matrix = matrices::left_mul(&mut vec![
-            &view_matrix,
-            &translation_matrix,
-            &scale_matrix,
-            &rotation_matrix,
-        ]);
-
-        let perspective_matrix = matrices::perspective(aspect_ratio, TAU / 6.0, 0.1, 1024.0);
-
-        // clear screen with a nice blue color
-        target.clear_color_and_depth((0.0, 0.4, 0.7, 1.0), 1.0);
-
-        // draw
-        target
-            .draw(
-                (&teapot_positions, &teapot_normals),
-                &teapot_indices,
-                &program,
-                &uniform! {
-                    u_light: light,
-                    u_perspective: perspective_matrix,
-                    // u_rotation: rotation_matrix,
-                    // u_scale: scale_matrix,
-                    // u_translation: translation_matrix,
-                    // u_view: view_matrix,
-                    model_view: model_view_matrix,
-                },
-                &glium::DrawParameters {
-                    depth: glium::Depth {
-                        test: glium::draw_parameters::DepthTest::IfLess,
-                        write: true,
-                        ..glium::Depth::default()
-                    },
-                    backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
-                    ..glium::DrawParameters::default()
-                },
-            )
-            .unwrap();
-        target.finish().unwrap();
-    }); // event_loop::run(..)
+    const fn cursor_grabbed(&self) -> bool {
+        match self {
+            Self::Orbit(_) => false,
+            Self::Fly(fly) => fly.cursor_grabbed(),
+        }
+    }
+}
+
+fn grab_cursor(display: &glium::Display, grab: bool) {
+    let gl_window = display.gl_window();
+    let window = gl_window.window();
+    if grab {
+        // not every platform supports both modes, locked is the better one for mouse-look
+        let grabbed = window
+            .set_cursor_grab(glutin::window::CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(glutin::window::CursorGrabMode::Confined));
+        if let Err(error) = grabbed {
+            eprintln!("couldn't grab the cursor: {error}");
+        }
+    } else {
+        window
+            .set_cursor_grab(glutin::window::CursorGrabMode::None)
+            .ok();
+    }
+    window.set_cursor_visible(!grab);
 }
@@ -1,114 +1,1040 @@
-#[allow(clippy::suboptimal_flops)]
-pub fn view(position: &[f32; 3], direction: &[f32; 3], up: &[f32; 3]) -> [[f32; 4]; 4] {
-    // source: https://glium.github.io/glium/book/tuto-12-camera.html
-    let f = {
-        let f = direction;
-        let len = f[0] * f[0] + f[1] * f[1] + f[2] * f[2];
-        let len = len.sqrt();
-        [f[0] / len, f[1] / len, f[2] / len]
-    };
-
-    let s = [
-        up[1] * f[2] - up[2] * f[1],
-        up[2] * f[0] - up[0] * f[2],
-        up[0] * f[1] - up[1] * f[0],
-    ];
-
-    let s_norm = {
-        let len = s[0] * s[0] + s[1] * s[1] + s[2] * s[2];
-        let len = len.sqrt();
-        [s[0] / len, s[1] / len, s[2] / len]
-    };
-
-    let u = [
-        f[1] * s_norm[2] - f[2] * s_norm[1],
-        f[2] * s_norm[0] - f[0] * s_norm[2],
-        f[0] * s_norm[1] - f[1] * s_norm[0],
-    ];
-
-    let p = [
-        -position[0] * s_norm[0] - position[1] * s_norm[1] - position[2] * s_norm[2],
-        -position[0] * u[0] - position[1] * u[1] - position[2] * u[2],
-        -position[0] * f[0] - position[1] * f[1] - position[2] * f[2],
-    ];
-
-    [
-        [s_norm[0], u[0], f[0], 0.0],
-        [s_norm[1], u[1], f[1], 0.0],
-        [s_norm[2], u[2], f[2], 0.0],
-        [p[0], p[1], p[2], 1.0],
-    ]
-}
-
-// unless we are doing couple of thousand operations of trigonometry each frame we can really do it on CPU
-// source: https://www.reddit.com/r/AskComputerScience/comments/22g1dg/how_is_trigonometry_computed_with_cpu_does_gpu/
-pub fn rotation(axis: Axis, angle: f32) -> [[f32; 4]; 4] {
-    match axis {
-        Axis::Y => [
-            [angle.cos(), angle.sin(), 0.0, 0.0],
-            [-angle.sin(), angle.cos(), 0.0, 0.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [0.0, 0.0, 0.0, 1.0],
-        ],
-        Axis::X => [
-            [1.0, 0.0, 0.0, 0.0],
-            [0.0, angle.cos(), angle.sin(), 0.0],
-            [0.0, -angle.sin(), angle.cos(), 0.0],
-            [0.0, 0.0, 0.0, 1.0],
-        ],
-        Axis::Z => [
-            [angle.cos(), 0.0, angle.sin(), 0.0],
-            [0.0, 1.0, 0.0, 0.0],
-            [-angle.sin(), 0.0, angle.cos(), 0.0],
+use glium::uniforms::{AsUniformValue, UniformValue};
+use std::iter::Product;
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod simd;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
+    pub const X: Self = Self::new(1.0, 0.0, 0.0);
+    pub const Y: Self = Self::new(0.0, 1.0, 0.0);
+    pub const Z: Self = Self::new(0.0, 0.0, 1.0);
+
+    #[must_use]
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    #[must_use]
+    pub const fn splat(v: f32) -> Self {
+        Self::new(v, v, v)
+    }
+
+    #[must_use]
+    pub fn dot(self, other: Self) -> f32 {
+        self.x
+            .mul_add(other.x, self.y.mul_add(other.y, self.z * other.z))
+    }
+
+    #[must_use]
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y.mul_add(other.z, -self.z * other.y),
+            self.z.mul_add(other.x, -self.x * other.z),
+            self.x.mul_add(other.y, -self.y * other.x),
+        )
+    }
+
+    #[must_use]
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    #[must_use]
+    pub fn normalized(self) -> Self {
+        self * (1.0 / self.length())
+    }
+
+    #[must_use]
+    pub const fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        Self::new(self.x * factor, self.y * factor, self.z * factor)
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl From<Vec3> for [f32; 3] {
+    fn from(v: Vec3) -> Self {
+        [v.x, v.y, v.z]
+    }
+}
+
+impl AsUniformValue for Vec3 {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        UniformValue::Vec3((*self).into())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Vec4 {
+    pub const ZERO: Self = Self::new(0.0, 0.0, 0.0, 0.0);
+
+    #[must_use]
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    #[must_use]
+    pub fn dot(self, other: Self) -> f32 {
+        self.x.mul_add(
+            other.x,
+            self.y
+                .mul_add(other.y, self.z.mul_add(other.z, self.w * other.w)),
+        )
+    }
+
+    #[must_use]
+    pub const fn truncate(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+}
+
+impl Add for Vec4 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(
+            self.x + other.x,
+            self.y + other.y,
+            self.z + other.z,
+            self.w + other.w,
+        )
+    }
+}
+
+impl Sub for Vec4 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(
+            self.x - other.x,
+            self.y - other.y,
+            self.z - other.z,
+            self.w - other.w,
+        )
+    }
+}
+
+impl Neg for Vec4 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, -self.w)
+    }
+}
+
+impl Mul<f32> for Vec4 {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        Self::new(
+            self.x * factor,
+            self.y * factor,
+            self.z * factor,
+            self.w * factor,
+        )
+    }
+}
+
+impl From<[f32; 4]> for Vec4 {
+    fn from([x, y, z, w]: [f32; 4]) -> Self {
+        Self::new(x, y, z, w)
+    }
+}
+
+impl From<Vec4> for [f32; 4] {
+    fn from(v: Vec4) -> Self {
+        [v.x, v.y, v.z, v.w]
+    }
+}
+
+impl AsUniformValue for Vec4 {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        UniformValue::Vec4((*self).into())
+    }
+}
+
+// (x, y, z) is the vector part, w the scalar part
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quat {
+    pub const IDENTITY: Self = Self::new(0.0, 0.0, 0.0, 1.0);
+
+    #[must_use]
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    #[must_use]
+    pub const fn vector(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    #[must_use]
+    pub fn dot(self, other: Self) -> f32 {
+        Vec4::from(self).dot(Vec4::from(other))
+    }
+
+    #[must_use]
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    #[must_use]
+    pub fn normalized(self) -> Self {
+        let len = self.length();
+        Self::new(self.x / len, self.y / len, self.z / len, self.w / len)
+    }
+
+    #[must_use]
+    pub const fn conjugate(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    // `axis` doesn't have to be normalized
+    #[must_use]
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let v = axis.normalized() * sin;
+        Self::new(v.x, v.y, v.z, cos)
+    }
+
+    // `x`, `y` and `z` are the angles about the respective world axes,
+    // `order` is the order in which those rotations are applied
+    #[must_use]
+    pub fn from_euler(order: EulerOrder, x: f32, y: f32, z: f32) -> Self {
+        let qx = Self::from_axis_angle(Vec3::X, x);
+        let qy = Self::from_axis_angle(Vec3::Y, y);
+        let qz = Self::from_axis_angle(Vec3::Z, z);
+        match order {
+            EulerOrder::XYZ => qz * qy * qx,
+            EulerOrder::XZY => qy * qz * qx,
+            EulerOrder::YXZ => qz * qx * qy,
+            EulerOrder::YZX => qx * qz * qy,
+            EulerOrder::ZXY => qy * qx * qz,
+            EulerOrder::ZYX => qx * qy * qz,
+        }
+    }
+
+    // assumes the upper-left 3x3 of `m` is a pure rotation
+    // source: https://www.euclideanspace.com/maths/geometry/rotations/conversions/matrixToQuaternion/
+    #[allow(clippy::suboptimal_flops)]
+    #[must_use]
+    pub fn from_rotation_matrix(m: &Mat4) -> Self {
+        let a = |row: usize, col: usize| m.0[col][row];
+        let trace = a(0, 0) + a(1, 1) + a(2, 2);
+        if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new(
+                (a(2, 1) - a(1, 2)) / s,
+                (a(0, 2) - a(2, 0)) / s,
+                (a(1, 0) - a(0, 1)) / s,
+                s / 4.0,
+            )
+        } else if a(0, 0) > a(1, 1) && a(0, 0) > a(2, 2) {
+            let s = (1.0 + a(0, 0) - a(1, 1) - a(2, 2)).sqrt() * 2.0;
+            Self::new(
+                s / 4.0,
+                (a(0, 1) + a(1, 0)) / s,
+                (a(0, 2) + a(2, 0)) / s,
+                (a(2, 1) - a(1, 2)) / s,
+            )
+        } else if a(1, 1) > a(2, 2) {
+            let s = (1.0 + a(1, 1) - a(0, 0) - a(2, 2)).sqrt() * 2.0;
+            Self::new(
+                (a(0, 1) + a(1, 0)) / s,
+                s / 4.0,
+                (a(1, 2) + a(2, 1)) / s,
+                (a(0, 2) - a(2, 0)) / s,
+            )
+        } else {
+            let s = (1.0 + a(2, 2) - a(0, 0) - a(1, 1)).sqrt() * 2.0;
+            Self::new(
+                (a(0, 2) + a(2, 0)) / s,
+                (a(1, 2) + a(2, 1)) / s,
+                s / 4.0,
+                (a(1, 0) - a(0, 1)) / s,
+            )
+        }
+        .normalized()
+    }
+
+    // rotation taking +Z to `forward` and +Y as close to `up` as possible, i.e. the orientation of
+    // something looking along `forward` in the view space of `Mat4::view`;
+    // `forward` must not be parallel to `up`
+    #[must_use]
+    pub fn look_rotation(forward: Vec3, up: Vec3) -> Self {
+        let f = forward.normalized();
+        let s = up.cross(f).normalized();
+        let u = f.cross(s);
+        Self::from_rotation_matrix(&Mat4::from_cols(
+            s.extend(0.0),
+            u.extend(0.0),
+            f.extend(0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+        ))
+    }
+
+    #[must_use]
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        // v' = v + 2w(q x v) + 2q x (q x v), cheaper than q * v * q^-1
+        let q = self.vector();
+        let t = q.cross(v) * 2.0;
+        v + t * self.w + q.cross(t)
+    }
+
+    // normalized linear interpolation, along the shorter arc
+    #[must_use]
+    pub fn nlerp(self, other: Self, t: f32) -> Self {
+        let other = if self.dot(other) < 0.0 { -other } else { other };
+        Self::from(Vec4::from(self) * (1.0 - t) + Vec4::from(other) * t).normalized()
+    }
+
+    // spherical linear interpolation, along the shorter arc
+    #[must_use]
+    pub fn slerp(self, other: Self, t: f32) -> Self {
+        let mut cos_theta = self.dot(other);
+        let other = if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            -other
+        } else {
+            other
+        };
+        // sin(theta) goes to 0 for almost equal rotations, nlerp is indistinguishable there
+        if cos_theta > 0.9995 {
+            return self.nlerp(other, t);
+        }
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+        Self::from(Vec4::from(self) * a + Vec4::from(other) * b)
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Add for Quat {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(
+            self.x + other.x,
+            self.y + other.y,
+            self.z + other.z,
+            self.w + other.w,
+        )
+    }
+}
+
+impl Neg for Quat {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, -self.w)
+    }
+}
+
+// Hamilton product, `a * b` applies `b` first and then `a`
+impl Mul for Quat {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let (v1, v2) = (self.vector(), other.vector());
+        let v = v2 * self.w + v1 * other.w + v1.cross(v2);
+        Self::new(v.x, v.y, v.z, self.w.mul_add(other.w, -v1.dot(v2)))
+    }
+}
+
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+
+    fn mul(self, v: Vec3) -> Vec3 {
+        self.rotate(v)
+    }
+}
+
+impl From<Quat> for Vec4 {
+    fn from(q: Quat) -> Self {
+        Self::new(q.x, q.y, q.z, q.w)
+    }
+}
+
+impl From<Vec4> for Quat {
+    fn from(v: Vec4) -> Self {
+        Self::new(v.x, v.y, v.z, v.w)
+    }
+}
+
+impl From<Quat> for Mat4 {
+    // `q` has to be normalized
+    fn from(q: Quat) -> Self {
+        let (x2, y2, z2) = (q.x + q.x, q.y + q.y, q.z + q.z);
+        let (xx, yy, zz) = (q.x * x2, q.y * y2, q.z * z2);
+        let (xy, xz, yz) = (q.x * y2, q.x * z2, q.y * z2);
+        let (wx, wy, wz) = (q.w * x2, q.w * y2, q.w * z2);
+        Self([
+            [1.0 - (yy + zz), xy + wz, xz - wy, 0.0],
+            [xy - wz, 1.0 - (xx + zz), yz + wx, 0.0],
+            [xz + wy, yz - wx, 1.0 - (xx + yy), 0.0],
             [0.0, 0.0, 0.0, 1.0],
-        ],
+        ])
+    }
+}
+
+impl From<Mat4> for Quat {
+    fn from(m: Mat4) -> Self {
+        Self::from_rotation_matrix(&m)
+    }
+}
+
+// order in which `Quat::from_euler` applies the per-axis rotations, e.g. `XYZ` rotates about X first
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EulerOrder {
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    ZXY,
+    ZYX,
+}
+
+// column-major like `Mat4`, used for the normal matrix: self.0[column][row]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mat3(pub [[f32; 3]; 3]);
+
+impl Mat3 {
+    pub const IDENTITY: Self = Self([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+
+    #[must_use]
+    pub const fn transpose(&self) -> Self {
+        let m = &self.0;
+        Self([
+            [m[0][0], m[1][0], m[2][0]],
+            [m[0][1], m[1][1], m[2][1]],
+            [m[0][2], m[1][2], m[2][2]],
+        ])
+    }
+
+    #[must_use]
+    pub fn determinant(&self) -> f32 {
+        let [c0, c1, c2] = self.0.map(Vec3::from);
+        c0.dot(c1.cross(c2))
+    }
+
+    // None if the matrix is singular
+    #[must_use]
+    pub fn inverse(&self) -> Option<Self> {
+        let [c0, c1, c2] = self.0.map(Vec3::from);
+        let det = c0.dot(c1.cross(c2));
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        // rows of the inverse are the cross products of the columns divided by the determinant
+        let inv_det = 1.0 / det;
+        let r0 = c1.cross(c2) * inv_det;
+        let r1 = c2.cross(c0) * inv_det;
+        let r2 = c0.cross(c1) * inv_det;
+        Some(Self([r0.into(), r1.into(), r2.into()]).transpose())
+    }
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Self::IDENTITY
     }
 }
 
-pub const fn translation(x: f32, y: f32, z: f32) -> [[f32; 4]; 4] {
-    [
+impl From<Mat4> for Mat3 {
+    // upper-left 3x3 block, drops the translation
+    fn from(m: Mat4) -> Self {
+        let m = &m.0;
+        Self([
+            [m[0][0], m[0][1], m[0][2]],
+            [m[1][0], m[1][1], m[1][2]],
+            [m[2][0], m[2][1], m[2][2]],
+        ])
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, v: Vec3) -> Vec3 {
+        let [c0, c1, c2] = self.0.map(Vec3::from);
+        c0 * v.x + c1 * v.y + c2 * v.z
+    }
+}
+
+impl AsUniformValue for Mat3 {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        UniformValue::Mat3(self.0)
+    }
+}
+
+// column-major, the same layout glium uses for `mat4` uniforms: self.0[column][row]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mat4(pub [[f32; 4]; 4]);
+
+impl Mat4 {
+    pub const IDENTITY: Self = Self([
         [1.0, 0.0, 0.0, 0.0],
         [0.0, 1.0, 0.0, 0.0],
         [0.0, 0.0, 1.0, 0.0],
-        [x, y, z, 1.0],
-    ]
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    #[must_use]
+    pub const fn from_cols(c0: Vec4, c1: Vec4, c2: Vec4, c3: Vec4) -> Self {
+        Self([
+            [c0.x, c0.y, c0.z, c0.w],
+            [c1.x, c1.y, c1.z, c1.w],
+            [c2.x, c2.y, c2.z, c2.w],
+            [c3.x, c3.y, c3.z, c3.w],
+        ])
+    }
+
+    #[must_use]
+    pub const fn col(&self, i: usize) -> Vec4 {
+        let c = self.0[i];
+        Vec4::new(c[0], c[1], c[2], c[3])
+    }
+
+    #[must_use]
+    pub const fn row(&self, i: usize) -> Vec4 {
+        let m = &self.0;
+        Vec4::new(m[0][i], m[1][i], m[2][i], m[3][i])
+    }
+
+    #[must_use]
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        let v = *self * point.extend(1.0);
+        v.truncate() * (1.0 / v.w)
+    }
+
+    // `transform_point` for a whole batch, in place; vectorized with the `simd` feature
+    pub fn transform_points(&self, points: &mut [Vec3]) {
+        #[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+        simd::transform_points(self, points);
+        #[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+        for point in points {
+            *point = self.transform_point(*point);
+        }
+    }
+
+    #[must_use]
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        (*self * vector.extend(0.0)).truncate()
+    }
+
+    #[must_use]
+    pub const fn transpose(&self) -> Self {
+        Self::from_cols(self.row(0), self.row(1), self.row(2), self.row(3))
+    }
+
+    #[allow(clippy::suboptimal_flops)]
+    #[must_use]
+    pub fn determinant(&self) -> f32 {
+        let (s, c) = self.sub_determinants();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    // None if the matrix is singular
+    // Laplace expansion over the 2x2 sub-determinants of the top and bottom row pairs
+    #[allow(clippy::suboptimal_flops)]
+    #[must_use]
+    pub fn inverse(&self) -> Option<Self> {
+        let a = |row: usize, col: usize| self.0[col][row];
+        let (s, c) = self.sub_determinants();
+        let det = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        // b[row][col] of the inverse, transposed into column-major at the end
+        let b = [
+            [
+                a(1, 1) * c[5] - a(1, 2) * c[4] + a(1, 3) * c[3],
+                -a(0, 1) * c[5] + a(0, 2) * c[4] - a(0, 3) * c[3],
+                a(3, 1) * s[5] - a(3, 2) * s[4] + a(3, 3) * s[3],
+                -a(2, 1) * s[5] + a(2, 2) * s[4] - a(2, 3) * s[3],
+            ],
+            [
+                -a(1, 0) * c[5] + a(1, 2) * c[2] - a(1, 3) * c[1],
+                a(0, 0) * c[5] - a(0, 2) * c[2] + a(0, 3) * c[1],
+                -a(3, 0) * s[5] + a(3, 2) * s[2] - a(3, 3) * s[1],
+                a(2, 0) * s[5] - a(2, 2) * s[2] + a(2, 3) * s[1],
+            ],
+            [
+                a(1, 0) * c[4] - a(1, 1) * c[2] + a(1, 3) * c[0],
+                -a(0, 0) * c[4] + a(0, 1) * c[2] - a(0, 3) * c[0],
+                a(3, 0) * s[4] - a(3, 1) * s[2] + a(3, 3) * s[0],
+                -a(2, 0) * s[4] + a(2, 1) * s[2] - a(2, 3) * s[0],
+            ],
+            [
+                -a(1, 0) * c[3] + a(1, 1) * c[1] - a(1, 2) * c[0],
+                a(0, 0) * c[3] - a(0, 1) * c[1] + a(0, 2) * c[0],
+                -a(3, 0) * s[3] + a(3, 1) * s[1] - a(3, 2) * s[0],
+                a(2, 0) * s[3] - a(2, 1) * s[1] + a(2, 2) * s[0],
+            ],
+        ];
+
+        Some(Self(b.map(|row| row.map(|x| x * inv_det))).transpose())
+    }
+
+    // 2x2 determinants of the top two rows (s) and the bottom two rows (c)
+    #[allow(clippy::suboptimal_flops)]
+    fn sub_determinants(&self) -> ([f32; 6], [f32; 6]) {
+        let a = |row: usize, col: usize| self.0[col][row];
+        let s = [
+            a(0, 0) * a(1, 1) - a(1, 0) * a(0, 1),
+            a(0, 0) * a(1, 2) - a(1, 0) * a(0, 2),
+            a(0, 0) * a(1, 3) - a(1, 0) * a(0, 3),
+            a(0, 1) * a(1, 2) - a(1, 1) * a(0, 2),
+            a(0, 1) * a(1, 3) - a(1, 1) * a(0, 3),
+            a(0, 2) * a(1, 3) - a(1, 2) * a(0, 3),
+        ];
+        let c = [
+            a(2, 0) * a(3, 1) - a(3, 0) * a(2, 1),
+            a(2, 0) * a(3, 2) - a(3, 0) * a(2, 2),
+            a(2, 0) * a(3, 3) - a(3, 0) * a(2, 3),
+            a(2, 1) * a(3, 2) - a(3, 1) * a(2, 2),
+            a(2, 1) * a(3, 3) - a(3, 1) * a(2, 3),
+            a(2, 2) * a(3, 3) - a(3, 2) * a(2, 3),
+        ];
+        (s, c)
+    }
+
+    // inverse transpose of the upper-left 3x3, transforms normals correctly under non-uniform scale
+    // None if the matrix is singular
+    #[must_use]
+    pub fn normal_matrix(&self) -> Option<Mat3> {
+        Mat3::from(*self).inverse().map(|m| m.transpose())
+    }
+
+    #[must_use]
+    pub fn view(position: Vec3, direction: Vec3, up: Vec3) -> Self {
+        // source: https://glium.github.io/glium/book/tuto-12-camera.html
+        let f = direction.normalized();
+        let s = up.cross(f).normalized();
+        let u = f.cross(s);
+        let p = Vec3::new(-position.dot(s), -position.dot(u), -position.dot(f));
+
+        Self([
+            [s.x, u.x, f.x, 0.0],
+            [s.y, u.y, f.y, 0.0],
+            [s.z, u.z, f.z, 0.0],
+            [p.x, p.y, p.z, 1.0],
+        ])
+    }
+
+    // rotation by `angle` about an arbitrary `axis` through the origin (right-handed)
+    #[must_use]
+    pub fn rotation_about(axis: Vec3, angle: f32) -> Self {
+        Quat::from_axis_angle(axis, angle).into()
+    }
+
+    // unless we are doing couple of thousand operations of trigonometry each frame we can really do it on CPU
+    // source: https://www.reddit.com/r/AskComputerScience/comments/22g1dg/how_is_trigonometry_computed_with_cpu_does_gpu/
+    //
+    // right-handed rotation about the named axis, e.g. `Axis::Z` by TAU / 4 takes X to Y.
+    // The `Y` arm used to rotate about Z and the `Z` arm about Y in the opposite direction;
+    // callers relying on that should replace `rotation(Axis::Z, a)` with `rotation(Axis::Y, -a)`
+    // and `rotation(Axis::Y, a)` with `rotation(Axis::Z, a)`.
+    #[must_use]
+    pub fn rotation(axis: Axis, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        match axis {
+            Axis::X => Self([
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, cos, sin, 0.0],
+                [0.0, -sin, cos, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+            Axis::Y => Self([
+                [cos, 0.0, -sin, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [sin, 0.0, cos, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+            Axis::Z => Self([
+                [cos, sin, 0.0, 0.0],
+                [-sin, cos, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+        }
+    }
+
+    #[must_use]
+    pub const fn translation(x: f32, y: f32, z: f32) -> Self {
+        Self([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [x, y, z, 1.0],
+        ])
+    }
+
+    #[must_use]
+    pub const fn scale(factor: f32) -> Self {
+        Self::scale_xyz(factor, factor, factor)
+    }
+
+    #[must_use]
+    pub const fn scale_xyz(x: f32, y: f32, z: f32) -> Self {
+        Self([
+            [x, 0.0, 0.0, 0.0],
+            [0.0, y, 0.0, 0.0],
+            [0.0, 0.0, z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // `aspect_ratio` is width / height, `fov_angle` is the vertical field of view;
+    // maps z_near to -1 and z_far to 1 in NDC
+    #[must_use]
+    pub fn perspective(aspect_ratio: f32, fov_angle: f32, z_near: f32, z_far: f32) -> Self {
+        let f = 1.0 / (fov_angle / 2.0).tan();
+        Self([
+            [f / aspect_ratio, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, (z_far + z_near) / (z_far - z_near), 1.0],
+            [0.0, 0.0, -(2.0 * z_far * z_near) / (z_far - z_near), 0.0],
+        ])
+    }
+
+    // `perspective` with z_far taken to infinity
+    #[must_use]
+    pub fn perspective_infinite(aspect_ratio: f32, fov_angle: f32, z_near: f32) -> Self {
+        let f = 1.0 / (fov_angle / 2.0).tan();
+        Self([
+            [f / aspect_ratio, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 1.0],
+            [0.0, 0.0, -2.0 * z_near, 0.0],
+        ])
+    }
+
+    // maps z_near to 1 and z_far to -1, so depth has to be cleared to 0.0 and tested with `IfMore`.
+    // Reversed z only gains precision with a floating point depth buffer and a [0, 1] clip space
+    // depth range (glClipControl). Here depth is in [-1, 1] and the depth buffer is 24 bit fixed
+    // point, so this is just `perspective` mirrored, with the same precision.
+    #[must_use]
+    pub fn perspective_reversed_z(
+        aspect_ratio: f32,
+        fov_angle: f32,
+        z_near: f32,
+        z_far: f32,
+    ) -> Self {
+        let f = 1.0 / (fov_angle / 2.0).tan();
+        Self([
+            [f / aspect_ratio, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, -(z_far + z_near) / (z_far - z_near), 1.0],
+            [0.0, 0.0, (2.0 * z_far * z_near) / (z_far - z_near), 0.0],
+        ])
+    }
+
+    // reversed-z with z_far taken to infinity
+    #[must_use]
+    pub fn perspective_reversed_z_infinite(aspect_ratio: f32, fov_angle: f32, z_near: f32) -> Self {
+        let f = 1.0 / (fov_angle / 2.0).tan();
+        Self([
+            [f / aspect_ratio, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, -1.0, 1.0],
+            [0.0, 0.0, 2.0 * z_near, 0.0],
+        ])
+    }
+
+    // maps the box to the [-1, 1] cube, z_near to -1 and z_far to 1 (z grows away from the camera, as in `view`)
+    #[must_use]
+    pub fn orthographic(
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        z_near: f32,
+        z_far: f32,
+    ) -> Self {
+        Self([
+            [2.0 / (right - left), 0.0, 0.0, 0.0],
+            [0.0, 2.0 / (top - bottom), 0.0, 0.0],
+            [0.0, 0.0, 2.0 / (z_far - z_near), 0.0],
+            [
+                -(right + left) / (right - left),
+                -(top + bottom) / (top - bottom),
+                -(z_far + z_near) / (z_far - z_near),
+                1.0,
+            ],
+        ])
+    }
 }
 
-pub const fn scale(factor: f32) -> [[f32; 4]; 4] {
-    [
-        [factor, 0.0, 0.0, 0.0],
-        [0.0, factor, 0.0, 0.0],
-        [0.0, 0.0, factor, 0.0],
-        [0.0, 0.0, 0.0, 1.0],
-    ]
+// projection parameters independent of the window size, so the matrix can be rebuilt on resize
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    Perspective {
+        fov_angle: f32,
+        z_near: f32,
+        z_far: f32,
+    },
+    InfinitePerspective {
+        fov_angle: f32,
+        z_near: f32,
+    },
+    // `z_far` is `None` for an infinite far plane
+    ReversedZ {
+        fov_angle: f32,
+        z_near: f32,
+        z_far: Option<f32>,
+    },
+    // `height` of the visible box, its width follows from the aspect ratio
+    Orthographic {
+        height: f32,
+        z_near: f32,
+        z_far: f32,
+    },
 }
 
-pub fn perspective(aspect_ratio: f32, fov_angle: f32, z_near: f32, z_far: f32) -> [[f32; 4]; 4] {
-    let f = 1.0 / (fov_angle / 2.0).tan();
-    [
-        [f * aspect_ratio, 0.0, 0.0, 0.0],
-        [0.0, f, 0.0, 0.0],
-        [0.0, 0.0, (z_far + z_near) / (z_far - z_near), 1.0],
-        [0.0, 0.0, -(2.0 * z_far * z_near) / (z_far - z_near), 0.0],
-    ]
+impl Projection {
+    #[must_use]
+    pub fn matrix(&self, aspect_ratio: f32) -> Mat4 {
+        match *self {
+            Self::Perspective {
+                fov_angle,
+                z_near,
+                z_far,
+            } => Mat4::perspective(aspect_ratio, fov_angle, z_near, z_far),
+            Self::InfinitePerspective { fov_angle, z_near } => {
+                Mat4::perspective_infinite(aspect_ratio, fov_angle, z_near)
+            }
+            Self::ReversedZ {
+                fov_angle,
+                z_near,
+                z_far: Some(z_far),
+            } => Mat4::perspective_reversed_z(aspect_ratio, fov_angle, z_near, z_far),
+            Self::ReversedZ {
+                fov_angle,
+                z_near,
+                z_far: None,
+            } => Mat4::perspective_reversed_z_infinite(aspect_ratio, fov_angle, z_near),
+            Self::Orthographic {
+                height,
+                z_near,
+                z_far,
+            } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * aspect_ratio;
+                Mat4::orthographic(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    z_near,
+                    z_far,
+                )
+            }
+        }
+    }
+
+    // reversed-z projections need the depth buffer cleared to 0.0 and `DepthTest::IfMore`
+    #[must_use]
+    pub const fn is_reversed_z(&self) -> bool {
+        matches!(self, Self::ReversedZ { .. })
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        product(&self, &other)
+    }
+}
+
+// left to right, starting from the first matrix so n matrices cost n - 1 products
+impl Product for Mat4 {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|acc, m| product(&acc, &m))
+            .unwrap_or(Self::IDENTITY)
+    }
+}
+
+impl<'a> Product<&'a Self> for Mat4 {
+    fn product<I: Iterator<Item = &'a Self>>(mut iter: I) -> Self {
+        iter.next().map_or(Self::IDENTITY, |first| {
+            iter.fold(*first, |acc, m| product(&acc, m))
+        })
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+
+    fn mul(self, v: Vec4) -> Vec4 {
+        Vec4::new(
+            self.row(0).dot(v),
+            self.row(1).dot(v),
+            self.row(2).dot(v),
+            self.row(3).dot(v),
+        )
+    }
+}
+
+impl Add for Mat4 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut result = self.0;
+        for (col, other_col) in result.iter_mut().zip(other.0) {
+            for (a, b) in col.iter_mut().zip(other_col) {
+                *a += b;
+            }
+        }
+        Self(result)
+    }
+}
+
+impl Neg for Mat4 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.map(|col| col.map(Neg::neg)))
+    }
+}
+
+impl From<[[f32; 4]; 4]> for Mat4 {
+    fn from(m: [[f32; 4]; 4]) -> Self {
+        Self(m)
+    }
+}
+
+impl From<Mat4> for [[f32; 4]; 4] {
+    fn from(m: Mat4) -> Self {
+        m.0
+    }
+}
+
+impl AsUniformValue for Mat4 {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        UniformValue::Mat4(self.0)
+    }
+}
+
+// SSE on x86_64 and NEON on aarch64 with the `simd` feature, `product_scalar` everywhere else
+#[must_use]
+pub fn product(mat1: &Mat4, mat2: &Mat4) -> Mat4 {
+    #[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    return simd::product(mat1, mat2);
+    #[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    return product_scalar(mat1, mat2);
 }
 
 // naive iterating algorithm
-pub fn product(mat1: &[[f32; 4]; 4], mat2: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+#[allow(clippy::needless_range_loop)]
+#[must_use]
+pub fn product_scalar(mat1: &Mat4, mat2: &Mat4) -> Mat4 {
     let mut result = [[0.0; 4]; 4];
 
     for i in 0..4 {
         for j in 0..4 {
             for k in 0..4 {
-                result[i][j] += mat1[k][j] * mat2[i][k];
+                result[i][j] += mat1.0[k][j] * mat2.0[i][k];
             }
         }
     }
 
-    result
+    Mat4(result)
+}
+
+// product of the matrices left to right, `compose(&[a, b, c]) == a * b * c`, identity when empty
+#[must_use]
+pub fn compose(matrices: &[Mat4]) -> Mat4 {
+    matrices.iter().product()
 }
 
-pub fn left_mul(matrices: &mut Vec<&[[f32;4];4]>) -> [[f32;4];4] {
+// `compose` for a fixed number of borrowed matrices, e.g. `compose_n([&view, &model])`
+#[must_use]
+pub fn compose_n<const N: usize>(matrices: [&Mat4; N]) -> Mat4 {
+    matrices.into_iter().product()
+}
+
+/// # Panics
+/// If `matrices` is empty.
+#[deprecated(
+    note = "use `compose`, `compose_n` or `Iterator::product`, they don't allocate and return the identity for no matrices"
+)]
+pub fn left_mul(matrices: &mut Vec<&Mat4>) -> Mat4 {
     if matrices.len() > 1 {
         let right = matrices.pop().unwrap();
         product(&left_mul(matrices), right)
@@ -117,8 +1043,406 @@ pub fn left_mul(matrices: &mut Vec<&[[f32;4];4]>) -> [[f32;4];4] {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Axis {
     X,
     Y,
     Z,
 }
+
+impl Axis {
+    #[must_use]
+    pub const fn unit(self) -> Vec3 {
+        match self {
+            Self::X => Vec3::X,
+            Self::Y => Vec3::Y,
+            Self::Z => Vec3::Z,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    const EPSILON: f32 = 1e-5;
+
+    // every multiple of TAU / 64 over two full turns in both directions, plus a few irrational-ish angles
+    fn angles() -> impl Iterator<Item = f32> {
+        #[allow(clippy::cast_precision_loss)]
+        (-128..=128)
+            .map(|i| i as f32 * TAU / 64.0)
+            .chain([0.1, 1.0, 2.5, -0.7, 3.3])
+    }
+
+    fn assert_vec_eq(actual: Vec3, expected: Vec3, context: &str) {
+        assert!(
+            (actual - expected).length() < EPSILON,
+            "{context}: expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    // images of the basis vectors under a right-handed rotation by `angle` about `axis`,
+    // written out by hand rather than derived from any code under test
+    fn expected_basis(axis: Axis, angle: f32) -> [Vec3; 3] {
+        let (sin, cos) = angle.sin_cos();
+        match axis {
+            Axis::X => [Vec3::X, Vec3::new(0.0, cos, sin), Vec3::new(0.0, -sin, cos)],
+            Axis::Y => [Vec3::new(cos, 0.0, -sin), Vec3::Y, Vec3::new(sin, 0.0, cos)],
+            Axis::Z => [Vec3::new(cos, sin, 0.0), Vec3::new(-sin, cos, 0.0), Vec3::Z],
+        }
+    }
+
+    #[test]
+    fn rotation_maps_basis_vectors() {
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            for angle in angles() {
+                let m = Mat4::rotation(axis, angle);
+                let expected = expected_basis(axis, angle);
+                for (basis, expected) in [Vec3::X, Vec3::Y, Vec3::Z].into_iter().zip(expected) {
+                    let context = format!("{axis:?} by {angle} applied to {basis:?}");
+                    assert_vec_eq(m.transform_vector(basis), expected, &context);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_quarter_turns_cycle_the_axes() {
+        let quarter = TAU / 4.0;
+        assert_vec_eq(
+            Mat4::rotation(Axis::X, quarter).transform_vector(Vec3::Y),
+            Vec3::Z,
+            "X quarter turn",
+        );
+        assert_vec_eq(
+            Mat4::rotation(Axis::Y, quarter).transform_vector(Vec3::Z),
+            Vec3::X,
+            "Y quarter turn",
+        );
+        assert_vec_eq(
+            Mat4::rotation(Axis::Z, quarter).transform_vector(Vec3::X),
+            Vec3::Y,
+            "Z quarter turn",
+        );
+    }
+
+    #[test]
+    fn rotation_keeps_its_axis_fixed() {
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            for angle in angles() {
+                let m = Mat4::rotation(axis, angle);
+                let v = axis.unit() * 3.0;
+                assert_vec_eq(m.transform_vector(v), v, &format!("{axis:?} by {angle}"));
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_is_orthonormal_and_proper() {
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            for angle in angles() {
+                let m = Mat4::rotation(axis, angle);
+                assert!((m.determinant() - 1.0).abs() < EPSILON);
+                let identity = m * m.transpose();
+                for (col, expected) in identity.0.iter().zip(Mat4::IDENTITY.0) {
+                    for (a, b) in col.iter().zip(expected) {
+                        assert!((a - b).abs() < EPSILON, "{axis:?} by {angle}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_inverts_with_negated_angle() {
+        let v = Vec3::new(0.3, -1.2, 2.0);
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            for angle in angles() {
+                let there_and_back = Mat4::rotation(axis, -angle) * Mat4::rotation(axis, angle);
+                assert_vec_eq(there_and_back.transform_vector(v), v, &format!("{axis:?}"));
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_agrees_with_rotation_about_and_quat() {
+        let v = Vec3::new(0.3, -1.2, 2.0);
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            for angle in angles() {
+                let context = format!("{axis:?} by {angle}");
+                let expected = Mat4::rotation(axis, angle).transform_vector(v);
+                let about = Mat4::rotation_about(axis.unit(), angle).transform_vector(v);
+                let quat = Quat::from_axis_angle(axis.unit(), angle) * v;
+                assert_vec_eq(about, expected, &context);
+                assert_vec_eq(quat, expected, &context);
+            }
+        }
+    }
+
+    fn assert_mat_eq(actual: &Mat4, expected: &Mat4, tolerance: f32, context: &str) {
+        for (actual_col, expected_col) in actual.0.iter().zip(expected.0) {
+            for (a, b) in actual_col.iter().zip(expected_col) {
+                assert!(
+                    (a - b).abs() < tolerance,
+                    "{context}: expected {expected:?}, got {actual:?}"
+                );
+            }
+        }
+    }
+
+    // a shear mixing x into y and z, and y into z, after a translation
+    fn sheared() -> Mat4 {
+        Mat4([
+            [1.0, 0.5, 0.3, 0.0],
+            [0.0, 1.0, 0.2, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [1.0, -2.0, 3.0, 1.0],
+        ])
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let trs = Mat4::translation(1.0, -2.0, 3.0)
+            * Mat4::rotation_about(Vec3::new(1.0, 2.0, -0.5), 0.7)
+            * Mat4::scale_xyz(2.0, 0.5, 3.0);
+        let projection = Mat4::perspective(1.5, TAU / 6.0, 0.1, 100.0);
+        for (m, name) in [
+            (trs, "TRS"),
+            (sheared(), "shear"),
+            (projection, "projection"),
+        ] {
+            let inverse = m.inverse().unwrap();
+            assert_mat_eq(&(m * inverse), &Mat4::IDENTITY, 1e-4, name);
+            assert_mat_eq(&(inverse * m), &Mat4::IDENTITY, 1e-4, name);
+        }
+
+        let m3 = Mat3::from(sheared());
+        let inverse = m3.inverse().unwrap();
+        for v in [Vec3::X, Vec3::Y, Vec3::Z, Vec3::new(0.3, -1.2, 2.0)] {
+            assert_vec_eq(m3 * (inverse * v), v, "Mat3 shear");
+        }
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        assert_eq!(Mat4::scale_xyz(1.0, 0.0, 1.0).inverse(), None);
+        // the third column is the sum of the first two
+        let dependent = Mat4([
+            [1.0, 2.0, 3.0, 0.0],
+            [4.0, 5.0, 6.0, 0.0],
+            [5.0, 7.0, 9.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_eq!(dependent.inverse(), None);
+        assert_eq!(Mat3::from(dependent).inverse(), None);
+        assert_eq!(dependent.normal_matrix(), None);
+    }
+
+    #[test]
+    fn determinant_of_a_scale_is_the_product_of_its_factors() {
+        let m = Mat4::scale_xyz(2.0, 3.0, 4.0);
+        assert!((m.determinant() - 24.0).abs() < EPSILON);
+        assert!((Mat3::from(m).determinant() - 24.0).abs() < EPSILON);
+        assert!((Mat4::scale_xyz(2.0, -3.0, 4.0).determinant() + 24.0).abs() < EPSILON);
+        assert!((sheared().determinant() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn normal_matrix_is_the_inverse_transpose_and_keeps_normals_perpendicular() {
+        let m = Mat4::translation(1.0, 2.0, 3.0)
+            * Mat4::rotation(Axis::Y, 0.4)
+            * Mat4::scale_xyz(4.0, 1.0, 0.25);
+        // the upper-left 3x3 of an affine matrix's inverse is the inverse of its upper-left 3x3
+        let expected = Mat3::from(m.inverse().unwrap().transpose());
+        let normal_matrix = m.normal_matrix().unwrap();
+        for (col, expected) in normal_matrix.0.iter().zip(expected.0) {
+            for (a, b) in col.iter().zip(expected) {
+                assert!((a - b).abs() < EPSILON, "{normal_matrix:?} != {expected:?}");
+            }
+        }
+
+        // a slanted plane: under the non-uniform scale the normal only stays perpendicular to
+        // the plane's tangents with the normal matrix
+        let normal = Vec3::new(1.0, 1.0, 1.0).normalized();
+        let tangents = [Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 1.0, -1.0)];
+        for tangent in tangents {
+            let tangent = m.transform_vector(tangent);
+            assert!((normal_matrix * normal).dot(tangent).abs() < EPSILON);
+            assert!(m.transform_vector(normal).dot(tangent).abs() > 0.1);
+        }
+    }
+
+    // q and -q are the same rotation
+    fn assert_quat_eq(actual: Quat, expected: Quat, context: &str) {
+        let (actual, expected) = (Vec4::from(actual), Vec4::from(expected));
+        let distance = |d: Vec4| d.dot(d).sqrt();
+        assert!(
+            distance(actual - expected).min(distance(actual + expected)) < 1e-4,
+            "{context}: expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn euler_orders_apply_the_axis_rotations_in_order() {
+        let (x, y, z) = (0.3, -1.1, 2.0);
+        let [rx, ry, rz] = [(Axis::X, x), (Axis::Y, y), (Axis::Z, z)]
+            .map(|(axis, angle)| Mat4::rotation(axis, angle));
+        // the first rotation applied is the rightmost factor
+        let orders = [
+            (EulerOrder::XYZ, rz * ry * rx),
+            (EulerOrder::XZY, ry * rz * rx),
+            (EulerOrder::YXZ, rz * rx * ry),
+            (EulerOrder::YZX, rx * rz * ry),
+            (EulerOrder::ZXY, ry * rx * rz),
+            (EulerOrder::ZYX, rx * ry * rz),
+        ];
+        let point = Vec3::new(0.3, -1.2, 2.0);
+        for (order, expected) in orders {
+            let rotation = Quat::from_euler(order, x, y, z);
+            let context = format!("{order:?}");
+            assert_vec_eq(rotation * point, expected.transform_vector(point), &context);
+            assert_mat_eq(&Mat4::from(rotation), &expected, EPSILON, &context);
+        }
+    }
+
+    #[test]
+    fn quat_survives_a_round_trip_through_mat4() {
+        let axes = [
+            Vec3::X,
+            Vec3::Y,
+            Vec3::Z,
+            Vec3::new(1.0, 2.0, -0.5),
+            Vec3::new(-1.0, 0.2, 0.7),
+        ];
+        // angles near a half turn make the trace negative, picking the branch of the largest
+        // diagonal element, which is a different one for each of the axes
+        let half = TAU / 2.0;
+        for axis in axes {
+            for angle in [0.0, 0.4, 2.0, half - 0.01, half, half + 0.01, -2.5] {
+                let q = Quat::from_axis_angle(axis, angle);
+                let context = format!("{axis:?} by {angle}");
+                assert_quat_eq(Quat::from(Mat4::from(q)), q, &context);
+            }
+        }
+    }
+
+    #[test]
+    fn look_rotation_points_z_forward_and_y_up() {
+        let forward = Vec3::new(1.0, -0.5, 2.0);
+        let q = Quat::look_rotation(forward, Vec3::Y);
+        assert_vec_eq(q * Vec3::Z, forward.normalized(), "forward");
+        let up = q * Vec3::Y;
+        assert!(up.dot(forward).abs() < EPSILON);
+        assert!(up.y > 0.0);
+        assert!((q.length() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn slerp_runs_along_the_shorter_arc_at_unit_length() {
+        let axis = Vec3::new(1.0, 2.0, -0.5);
+        let a = Quat::from_axis_angle(axis, 0.2);
+        let b = Quat::from_axis_angle(axis, 1.8);
+        assert_quat_eq(a.slerp(b, 0.0), a, "start");
+        assert_quat_eq(a.slerp(b, 1.0), b, "end");
+        assert_quat_eq(
+            a.slerp(b, 0.5),
+            Quat::from_axis_angle(axis, 1.0),
+            "midpoint",
+        );
+
+        // -b is the same rotation with a negative dot product, the long way round from a
+        assert!(a.dot(-b) < 0.0);
+        assert_quat_eq(
+            a.slerp(-b, 0.5),
+            Quat::from_axis_angle(axis, 1.0),
+            "shorter arc",
+        );
+        assert_quat_eq(a.nlerp(-b, 0.5), Quat::from_axis_angle(axis, 1.0), "nlerp");
+
+        for i in 0..=10 {
+            let t = f32::from(u8::try_from(i).unwrap()) / 10.0;
+            assert!(
+                (a.slerp(b, t).length() - 1.0).abs() < EPSILON,
+                "slerp at {t}"
+            );
+            assert!(
+                (a.slerp(-b, t).length() - 1.0).abs() < EPSILON,
+                "slerp at {t}"
+            );
+            assert!(
+                (a.nlerp(b, t).length() - 1.0).abs() < EPSILON,
+                "nlerp at {t}"
+            );
+        }
+        // close enough rotations take the nlerp path
+        let c = Quat::from_axis_angle(axis, 0.21);
+        assert!((a.slerp(c, 0.5).length() - 1.0).abs() < EPSILON);
+    }
+
+    // NDC depth of a point straight ahead of the camera at view-space depth `z`
+    fn ndc_depth(m: &Mat4, z: f32) -> f32 {
+        m.transform_point(Vec3::new(0.0, 0.0, z)).z
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_planes() {
+        let m = Mat4::perspective(1.5, TAU / 6.0, 0.1, 100.0);
+        assert!((ndc_depth(&m, 0.1) + 1.0).abs() < EPSILON);
+        assert!((ndc_depth(&m, 100.0) - 1.0).abs() < EPSILON);
+
+        let m = Mat4::perspective_reversed_z(1.5, TAU / 6.0, 0.1, 100.0);
+        assert!((ndc_depth(&m, 0.1) - 1.0).abs() < EPSILON);
+        assert!((ndc_depth(&m, 100.0) + 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn infinite_perspective_approaches_the_far_limit() {
+        let m = Mat4::perspective_infinite(1.5, TAU / 6.0, 0.1);
+        assert!((ndc_depth(&m, 0.1) + 1.0).abs() < EPSILON);
+        assert!(ndc_depth(&m, 1e6) < 1.0);
+        assert!((ndc_depth(&m, 1e6) - 1.0).abs() < 1e-3);
+
+        let m = Mat4::perspective_reversed_z_infinite(1.5, TAU / 6.0, 0.1);
+        assert!((ndc_depth(&m, 0.1) - 1.0).abs() < EPSILON);
+        assert!(ndc_depth(&m, 1e6) > -1.0);
+        assert!((ndc_depth(&m, 1e6) + 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn orthographic_maps_box_to_ndc_cube() {
+        let m = Mat4::orthographic(-2.0, 4.0, -1.0, 3.0, 0.5, 10.0);
+        assert_vec_eq(
+            m.transform_point(Vec3::new(-2.0, -1.0, 0.5)),
+            Vec3::new(-1.0, -1.0, -1.0),
+            "near corner",
+        );
+        assert_vec_eq(
+            m.transform_point(Vec3::new(4.0, 3.0, 10.0)),
+            Vec3::new(1.0, 1.0, 1.0),
+            "far corner",
+        );
+    }
+
+    #[test]
+    fn compose_multiplies_left_to_right() {
+        let a = Mat4::translation(1.0, 2.0, 3.0);
+        let b = Mat4::rotation(Axis::X, 0.4);
+        let c = Mat4::scale_xyz(2.0, 3.0, 4.0);
+        let expected = a * b * c;
+        assert_eq!(compose(&[a, b, c]), expected);
+        assert_eq!(compose_n([&a, &b, &c]), expected);
+        assert_eq!([a, b, c].into_iter().product::<Mat4>(), expected);
+        #[allow(deprecated)]
+        let legacy = left_mul(&mut vec![&a, &b, &c]);
+        assert_eq!(legacy, expected);
+    }
+
+    #[test]
+    fn compose_of_nothing_is_identity() {
+        assert_eq!(compose(&[]), Mat4::IDENTITY);
+        assert_eq!(compose_n([]), Mat4::IDENTITY);
+        assert_eq!(std::iter::empty::<Mat4>().product::<Mat4>(), Mat4::IDENTITY);
+    }
+}
@@ -0,0 +1,174 @@
+// SSE (x86_64) and NEON (aarch64) versions of the hot matrix paths.
+// Both are part of the baseline of their targets, so there is no runtime feature detection.
+//
+// `product` starts from zero and multiplies and adds in the same order as `product_scalar`,
+// never fusing, so the results are bit-identical to the scalar version.
+
+use super::{Mat4, Vec3};
+
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::{float32x4_t, vaddq_f32, vdupq_n_f32, vld1q_f32, vmulq_f32, vst1q_f32};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{__m128, _mm_add_ps, _mm_loadu_ps, _mm_mul_ps, _mm_set1_ps, _mm_storeu_ps};
+
+#[cfg(target_arch = "x86_64")]
+type F32x4 = __m128;
+#[cfg(target_arch = "aarch64")]
+type F32x4 = float32x4_t;
+
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn load(v: &[f32; 4]) -> F32x4 {
+    // SAFETY: `v` is valid for reading 4 f32, `loadu` has no alignment requirement
+    unsafe { _mm_loadu_ps(v.as_ptr()) }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn store(v: F32x4) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    // SAFETY: `out` is valid for writing 4 f32, `storeu` has no alignment requirement
+    unsafe { _mm_storeu_ps(out.as_mut_ptr(), v) };
+    out
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn splat(x: f32) -> F32x4 {
+    // SAFETY: SSE is part of the x86_64 baseline
+    unsafe { _mm_set1_ps(x) }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn mul_add(acc: F32x4, a: F32x4, b: F32x4) -> F32x4 {
+    // SAFETY: SSE is part of the x86_64 baseline
+    unsafe { _mm_add_ps(acc, _mm_mul_ps(a, b)) }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline]
+fn load(v: &[f32; 4]) -> F32x4 {
+    // SAFETY: `v` is valid for reading 4 f32
+    unsafe { vld1q_f32(v.as_ptr()) }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline]
+fn store(v: F32x4) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    // SAFETY: `out` is valid for writing 4 f32
+    unsafe { vst1q_f32(out.as_mut_ptr(), v) };
+    out
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline]
+fn splat(x: f32) -> F32x4 {
+    // SAFETY: NEON is part of the aarch64 baseline
+    unsafe { vdupq_n_f32(x) }
+}
+
+// separate multiply and add on purpose, `vfmaq_f32` would round differently from the scalar code
+#[cfg(target_arch = "aarch64")]
+#[inline]
+fn mul_add(acc: F32x4, a: F32x4, b: F32x4) -> F32x4 {
+    // SAFETY: NEON is part of the aarch64 baseline
+    unsafe { vaddq_f32(acc, vmulq_f32(a, b)) }
+}
+
+// column i of the result is the columns of `mat1` weighted by the entries of column i of `mat2`
+pub fn product(mat1: &Mat4, mat2: &Mat4) -> Mat4 {
+    let cols = mat1.0.each_ref().map(load);
+    Mat4(mat2.0.map(|col| {
+        // from zero like `product_scalar`, which makes -0.0 terms add up to 0.0 in both
+        let mut result = mul_add(splat(0.0), cols[0], splat(col[0]));
+        result = mul_add(result, cols[1], splat(col[1]));
+        result = mul_add(result, cols[2], splat(col[2]));
+        result = mul_add(result, cols[3], splat(col[3]));
+        store(result)
+    }))
+}
+
+pub fn transform_points(matrix: &Mat4, points: &mut [Vec3]) {
+    let cols = matrix.0.each_ref().map(load);
+    for point in points {
+        let mut result = mul_add(cols[3], cols[0], splat(point.x));
+        result = mul_add(result, cols[1], splat(point.y));
+        result = mul_add(result, cols[2], splat(point.z));
+        let [x, y, z, w] = store(result);
+        *point = Vec3::new(x, y, z) * (1.0 / w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{product_scalar, Axis, Mat4, Vec3};
+    use std::f32::consts::TAU;
+
+    fn matrices() -> Vec<Mat4> {
+        vec![
+            Mat4::IDENTITY,
+            Mat4::view(Vec3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 2.0), Vec3::Y),
+            Mat4::translation(0.3, -7.0, 0.8),
+            Mat4::scale_xyz(0.008, 3.0, -1.5),
+            Mat4::rotation(Axis::X, 1.3),
+            Mat4::rotation_about(Vec3::new(1.0, 2.0, -0.5), 0.7),
+            Mat4::perspective(1.5, TAU / 6.0, 0.1, 1024.0),
+            Mat4([
+                [1.0e-7, 3.5, -2.25, 1.0e6],
+                [0.1, 0.2, 0.3, 0.4],
+                [-9.0, 8.0, -7.0, 6.0],
+                [f32::MIN_POSITIVE, 1.0e-3, 12.5, -0.0],
+            ]),
+        ]
+    }
+
+    #[test]
+    fn product_is_bit_identical_to_scalar() {
+        for a in matrices() {
+            for b in matrices() {
+                let simd = super::product(&a, &b);
+                let scalar = product_scalar(&a, &b);
+                for (simd_col, scalar_col) in simd.0.iter().zip(scalar.0) {
+                    for (x, y) in simd_col.iter().zip(scalar_col) {
+                        assert_eq!(x.to_bits(), y.to_bits(), "{a:?} * {b:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn negative_zero_terms_sum_to_zero_like_scalar() {
+        let ones = Mat4([[1.0; 4]; 4]);
+        let negative_zeros = Mat4([[-0.0; 4]; 4]);
+        let simd = super::product(&ones, &negative_zeros);
+        assert_eq!(simd.0, product_scalar(&ones, &negative_zeros).0);
+        for x in simd.0.iter().flatten() {
+            assert_eq!(x.to_bits(), 0.0_f32.to_bits());
+        }
+    }
+
+    #[test]
+    fn transform_points_matches_scalar_within_tolerance() {
+        let points: Vec<Vec3> = (-50_i16..50)
+            .map(|i| {
+                let t = f32::from(i) * 0.37;
+                Vec3::new(t.sin() * 40.0, t * 0.5, t.cos() * 40.0)
+            })
+            .collect();
+        for m in matrices() {
+            let mut simd = points.clone();
+            super::transform_points(&m, &mut simd);
+            for (simd, point) in simd.iter().zip(&points) {
+                let scalar = m.transform_point(*point);
+                let tolerance = 1e-5 * scalar.length().max(1.0);
+                assert!(
+                    (*simd - scalar).length() <= tolerance,
+                    "{m:?} applied to {point:?}: {simd:?} vs {scalar:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+pub mod gltf;
+pub mod indices;
+pub mod normals;
+pub mod obj;
+pub mod optimize;
+pub mod tangents;
+pub mod validation;
+pub mod vertex;
+
+use crate::matrices::{Mat4, Vec3, Vec4};
+use crate::teapot;
+use std::ops::Range;
+
+// an indexed triangle list; `normals`, `texcoords` and `tangents` are either empty or one per
+// position
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub texcoords: Vec<[f32; 2]>,
+    // along increasing u, with the bitangent's handedness in w: bitangent = w * normal × tangent
+    pub tangents: Vec<Vec4>,
+    pub indices: Vec<u32>,
+    // consecutive runs of `indices`, covering all of them
+    pub groups: Vec<Group>,
+}
+
+// part of a mesh drawn with one material, from OBJ `g`/`o` and `usemtl` or a glTF primitive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    pub name: String,
+    pub material: Option<String>,
+    pub indices: Range<usize>,
+}
+
+impl Mesh {
+    // the built-in teapot, without the dummy vertex 0 of `teapot::VERTICES`
+    #[must_use]
+    pub fn teapot() -> Self {
+        let positions = teapot::VERTICES[1..].iter().map(teapot::Vertex::position);
+        let normals = teapot::NORMALS[1..].iter().map(teapot::Normal::normal);
+        let indices: Vec<u32> = teapot::INDICES.iter().map(|&i| u32::from(i) - 1).collect();
+        Self {
+            positions: positions.collect(),
+            normals: normals.collect(),
+            texcoords: Vec::new(),
+            tangents: Vec::new(),
+            groups: vec![Group {
+                name: "teapot".to_owned(),
+                material: None,
+                indices: 0..indices.len(),
+            }],
+            indices,
+        }
+    }
+
+    #[must_use]
+    pub const fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[must_use]
+    pub const fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    // smallest and largest coordinates, None for a mesh without positions
+    #[must_use]
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let first = *self.positions.first()?;
+        Some(self.positions.iter().fold((first, first), |(min, max), p| {
+            (
+                Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        }))
+    }
+
+    // reverses the order of every triangle's corners, turning front faces into back faces
+    pub fn flip_winding(&mut self) {
+        for triangle in self.indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+
+    /// Adds another mesh's vertices, triangles and groups after this one's,
+    /// an attribute only one of the two has is padded with zeros.
+    ///
+    /// # Panics
+    /// If the meshes have more than 2^32 vertices together.
+    pub fn append(&mut self, other: &Self) {
+        let offset = u32::try_from(self.positions.len()).expect("more than 2^32 vertices");
+        let (ours, theirs) = (self.positions.len(), other.positions.len());
+        append_attribute(&mut self.normals, ours, &other.normals, theirs, Vec3::ZERO);
+        append_attribute(
+            &mut self.texcoords,
+            ours,
+            &other.texcoords,
+            theirs,
+            [0.0; 2],
+        );
+        append_attribute(
+            &mut self.tangents,
+            ours,
+            &other.tangents,
+            theirs,
+            Vec4::ZERO,
+        );
+        self.positions.extend_from_slice(&other.positions);
+
+        let start = self.indices.len();
+        self.indices
+            .extend(other.indices.iter().map(|&index| index + offset));
+        self.groups.extend(other.groups.iter().map(|group| Group {
+            indices: group.indices.start + start..group.indices.end + start,
+            ..group.clone()
+        }));
+    }
+
+    // moves the positions by `matrix`, the normals by its normal matrix and the tangents
+    // like directions on the surface
+    pub fn transform(&mut self, matrix: &Mat4) {
+        matrix.transform_points(&mut self.positions);
+        if let Some(normal_matrix) = matrix.normal_matrix() {
+            for normal in &mut self.normals {
+                *normal = (normal_matrix * *normal).normalized();
+            }
+        }
+        // a mirroring matrix turns the bitangent the other way relative to normal × tangent
+        let handedness = matrix.determinant().signum();
+        for tangent in &mut self.tangents {
+            let direction = matrix.transform_vector(tangent.truncate()).normalized();
+            *tangent = direction.extend(tangent.w * handedness);
+        }
+    }
+}
+
+// `ours` followed by `theirs`, unless neither mesh has the attribute
+fn append_attribute<T: Copy>(
+    ours: &mut Vec<T>,
+    our_count: usize,
+    theirs: &[T],
+    their_count: usize,
+    zero: T,
+) {
+    if ours.is_empty() && theirs.is_empty() {
+        return;
+    }
+    ours.resize(our_count, zero);
+    if theirs.is_empty() {
+        ours.resize(our_count + their_count, zero);
+    } else {
+        ours.extend_from_slice(theirs);
+    }
+}
@@ -0,0 +1,639 @@
+// glTF 2.0, both `.gltf` with its buffers and images in separate files or data URIs and
+// binary `.glb`. Meshes, the node hierarchy and metallic-roughness materials are read;
+// cameras, skins, animations and morph targets are skipped.
+//
+// Only local files are read: relative URIs are looked up next to the model, and URIs with
+// any scheme other than `data:` are refused.
+use super::{Group, Mesh};
+use crate::image::Image;
+use crate::matrices::{Mat4, Quat, Vec3, Vec4};
+use crate::transform::Transform;
+use ::gltf::mesh::Mode;
+use base64::Engine;
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+// the default scene of a glTF file, or its first one if it doesn't name a default
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scene {
+    // one per glTF mesh, with a group per primitive named after the mesh
+    pub meshes: Vec<Mesh>,
+    // all nodes of the file, indexed like it
+    pub nodes: Vec<Node>,
+    // the nodes at the top of the scene's hierarchy
+    pub roots: Vec<usize>,
+    pub materials: Vec<Material>,
+    pub images: Vec<ImageSource>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub name: String,
+    // relative to the parent node
+    pub transform: Transform,
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+// a metallic-roughness material; where there's a texture, its texels scale the factor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    // what the groups of `Scene::meshes` refer to it by, `material <index>` if it has no name
+    pub name: String,
+    pub base_color: Vec4,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic: f32,
+    pub roughness: f32,
+    // metalness in the blue channel, roughness in the green one
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive: Vec3,
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    // fragments with an alpha below `cutoff` are discarded
+    Mask { cutoff: f32 },
+    Blend,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureRef {
+    // into `Scene::images`
+    pub image: usize,
+    // which texture coordinate set it's mapped with, meshes only get set 0
+    pub texcoord: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImageSource {
+    // a file next to the model, not read yet
+    File(PathBuf),
+    // a data URI or a buffer view, still encoded
+    Embedded {
+        mime_type: Option<String>,
+        data: Vec<u8>,
+    },
+}
+
+impl ImageSource {
+    /// Decodes the image, which only works for 8 bit RGB and RGBA PNGs.
+    ///
+    /// # Errors
+    /// If the file can't be read or the image isn't such a PNG.
+    pub fn load_png(&self) -> io::Result<Image> {
+        match self {
+            Self::File(path) => Image::load_png(path),
+            Self::Embedded { data, .. } => Image::read_png(data.as_slice()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GltfError {
+    Io { path: PathBuf, error: io::Error },
+    // not glTF, or glTF that doesn't validate
+    Format(::gltf::Error),
+    // a buffer or image somewhere other than a file in the model's directory or a data URI
+    ExternalUri(String),
+    // valid glTF whose data doesn't add up
+    Invalid(String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "couldn't read {}: {error}", path.display()),
+            Self::Format(error) => write!(f, "{error}"),
+            Self::ExternalUri(uri) => write!(f, "{uri} isn't a file next to the model"),
+            Self::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+impl Scene {
+    // each node's transform relative to the scene, None for nodes that aren't part of it
+    #[must_use]
+    pub fn world_matrices(&self) -> Vec<Option<Mat4>> {
+        let mut matrices = vec![None; self.nodes.len()];
+        let mut stack: Vec<(usize, Mat4)> = self
+            .roots
+            .iter()
+            .map(|&root| (root, Mat4::IDENTITY))
+            .collect();
+        while let Some((index, parent)) = stack.pop() {
+            // glTF forbids cycles and shared children, this just makes sure they can't hang us
+            if matrices[index].is_some() {
+                continue;
+            }
+            let node = &self.nodes[index];
+            let world = parent * node.transform.matrix();
+            matrices[index] = Some(world);
+            stack.extend(node.children.iter().map(|&child| (child, world)));
+        }
+        matrices
+    }
+
+    // every mesh of the scene moved into place, as one mesh
+    #[must_use]
+    pub fn flatten(&self) -> Mesh {
+        let mut flattened = Mesh::default();
+        for (node, world) in self.nodes.iter().zip(self.world_matrices()) {
+            let (Some(mesh), Some(world)) = (node.mesh, world) else {
+                continue;
+            };
+            let mut instance = self.meshes[mesh].clone();
+            instance.transform(&world);
+            // a mirroring transform turns the triangles inside out
+            if world.determinant() < 0.0 {
+                instance.flip_winding();
+            }
+            flattened.append(&instance);
+        }
+        flattened
+    }
+}
+
+/// Reads a `.gltf` or `.glb` file and the buffers and images it refers to.
+///
+/// # Errors
+/// If a file can't be read, isn't valid glTF or refers to something that isn't a local file.
+pub fn load(path: impl AsRef<Path>) -> Result<Scene, GltfError> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|error| GltfError::Io {
+        path: path.to_owned(),
+        error,
+    })?;
+    parse(&data, path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Parses the contents of a `.gltf` or `.glb` file, looking up relative URIs in `directory`.
+///
+/// # Errors
+/// Like [`load`].
+pub fn parse(data: &[u8], directory: &Path) -> Result<Scene, GltfError> {
+    let ::gltf::Gltf { document, mut blob } =
+        ::gltf::Gltf::from_slice(data).map_err(GltfError::Format)?;
+
+    let mut buffers = Vec::new();
+    for buffer in document.buffers() {
+        let data = match buffer.source() {
+            // only the first buffer of a GLB file can be its binary chunk
+            ::gltf::buffer::Source::Bin => blob
+                .take()
+                .ok_or_else(|| invalid(format!("buffer {} has no data", buffer.index())))?,
+            ::gltf::buffer::Source::Uri(uri) => match resolve(uri, directory)? {
+                Uri::Data { data, .. } => data,
+                Uri::File(path) => {
+                    std::fs::read(&path).map_err(|error| GltfError::Io { path, error })?
+                }
+            },
+        };
+        if data.len() < buffer.length() {
+            return Err(invalid(format!(
+                "buffer {} has {} bytes, expected {}",
+                buffer.index(),
+                data.len(),
+                buffer.length()
+            )));
+        }
+        buffers.push(data);
+    }
+
+    let images = document
+        .images()
+        .map(|image| match image.source() {
+            ::gltf::image::Source::View { view, mime_type } => {
+                let range = view.offset()..view.offset() + view.length();
+                let data = buffers[view.buffer().index()]
+                    .get(range)
+                    .ok_or_else(|| invalid(format!("image {} is out of bounds", image.index())))?;
+                Ok(ImageSource::Embedded {
+                    mime_type: Some(mime_type.to_owned()),
+                    data: data.to_vec(),
+                })
+            }
+            ::gltf::image::Source::Uri { uri, mime_type } => Ok(match resolve(uri, directory)? {
+                Uri::Data {
+                    mime_type: data_type,
+                    data,
+                } => ImageSource::Embedded {
+                    mime_type: mime_type.map(str::to_owned).or(Some(data_type)),
+                    data,
+                },
+                Uri::File(path) => ImageSource::File(path),
+            }),
+        })
+        .collect::<Result<_, GltfError>>()?;
+
+    let meshes = document
+        .meshes()
+        .map(|mesh| read_mesh(&mesh, &buffers))
+        .collect::<Result<_, _>>()?;
+
+    let nodes = document
+        .nodes()
+        .map(|node| {
+            let (translation, [x, y, z, w], scale) = node.transform().decomposed();
+            Node {
+                name: node.name().unwrap_or_default().to_owned(),
+                transform: Transform::from_translation(translation.into())
+                    .with_rotation(Quat::new(x, y, z, w))
+                    .with_scale(scale.into()),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                children: node.children().map(|child| child.index()).collect(),
+            }
+        })
+        .collect();
+
+    let roots = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .map(|scene| scene.nodes().map(|node| node.index()).collect())
+        .unwrap_or_default();
+
+    Ok(Scene {
+        meshes,
+        nodes,
+        roots,
+        materials: document
+            .materials()
+            .map(|material| read_material(&material))
+            .collect(),
+        images,
+    })
+}
+
+const fn invalid(message: String) -> GltfError {
+    GltfError::Invalid(message)
+}
+
+enum Uri {
+    Data { mime_type: String, data: Vec<u8> },
+    File(PathBuf),
+}
+
+fn resolve(uri: &str, directory: &Path) -> Result<Uri, GltfError> {
+    if let Some(data_uri) = uri.strip_prefix("data:") {
+        let (header, payload) = data_uri
+            .split_once(',')
+            .ok_or_else(|| invalid("a data URI without a comma".to_owned()))?;
+        return Ok(match header.strip_suffix(";base64") {
+            Some(mime_type) => Uri::Data {
+                mime_type: mime_type.to_owned(),
+                data: base64::engine::general_purpose::STANDARD
+                    .decode(payload)
+                    .map_err(|error| invalid(format!("bad base64 in a data URI: {error}")))?,
+            },
+            None => Uri::Data {
+                mime_type: header.to_owned(),
+                data: percent_decode(payload),
+            },
+        });
+    }
+
+    // `scheme:`, but not a single letter which would be a Windows drive
+    let scheme = uri.split_once(':').map_or("", |(scheme, _)| scheme);
+    let is_scheme = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if is_scheme {
+        return Err(GltfError::ExternalUri(uri.to_owned()));
+    }
+    // relative and inside the model's directory, whichever separator the exporter used
+    let path = String::from_utf8_lossy(&percent_decode(uri)).into_owned();
+    let inside = Path::new(&path.replace('\\', "/"))
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !inside {
+        return Err(GltfError::ExternalUri(uri.to_owned()));
+    }
+    Ok(Uri::File(directory.join(path)))
+}
+
+// `%xx` escapes to the bytes they stand for, anything else as is
+fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    decoded
+}
+
+fn read_mesh(mesh: &::gltf::Mesh<'_>, buffers: &[Vec<u8>]) -> Result<Mesh, GltfError> {
+    let name = mesh.name().unwrap_or_default();
+    let mut merged = Mesh::default();
+    for primitive in mesh.primitives() {
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+        let error = |message: &str| {
+            invalid(format!(
+                "primitive {} of mesh {}: {message}",
+                primitive.index(),
+                mesh.index()
+            ))
+        };
+
+        let positions: Vec<Vec3> = reader
+            .read_positions()
+            .ok_or_else(|| error("no positions"))?
+            .map(Vec3::from)
+            .collect();
+        let normals: Vec<Vec3> = reader
+            .read_normals()
+            .map(|normals| normals.map(Vec3::from).collect())
+            .unwrap_or_default();
+        let texcoords: Vec<[f32; 2]> = reader
+            .read_tex_coords(0)
+            .map(|texcoords| texcoords.into_f32().collect())
+            .unwrap_or_default();
+        let tangents: Vec<Vec4> = reader
+            .read_tangents()
+            .map(|tangents| tangents.map(Vec4::from).collect())
+            .unwrap_or_default();
+        if ![0, positions.len()].contains(&normals.len())
+            || ![0, positions.len()].contains(&texcoords.len())
+            || ![0, positions.len()].contains(&tangents.len())
+        {
+            return Err(error("attributes of different lengths"));
+        }
+
+        let count = u32::try_from(positions.len()).map_err(|_| error("too many vertices"))?;
+        let indices: Vec<u32> = reader.read_indices().map_or_else(
+            || (0..count).collect(),
+            |indices| indices.into_u32().collect(),
+        );
+        if indices.iter().any(|&index| index >= count) {
+            return Err(error("an index past the last vertex"));
+        }
+        let Some(indices) = triangles(primitive.mode(), &indices) else {
+            // points and lines
+            continue;
+        };
+
+        merged.append(&Mesh {
+            positions,
+            normals,
+            texcoords,
+            tangents,
+            groups: vec![Group {
+                name: name.to_owned(),
+                material: material_name(&primitive.material()),
+                indices: 0..indices.len(),
+            }],
+            indices,
+        });
+    }
+    Ok(merged)
+}
+
+// None for the default material of primitives that don't name one
+fn material_name(material: &::gltf::Material<'_>) -> Option<String> {
+    let index = material.index()?;
+    Some(
+        material
+            .name()
+            .map_or_else(|| format!("material {index}"), str::to_owned),
+    )
+}
+
+// a triangle list from a primitive's indices, None for points and lines
+fn triangles(mode: Mode, indices: &[u32]) -> Option<Vec<u32>> {
+    let corners = 0..indices.len().saturating_sub(2);
+    match mode {
+        Mode::Triangles => Some(indices[..indices.len() / 3 * 3].to_vec()),
+        // every other triangle of a strip is reversed to keep them all wound the same way
+        Mode::TriangleStrip => Some(
+            corners
+                .flat_map(|i| {
+                    if i % 2 == 0 {
+                        [indices[i], indices[i + 1], indices[i + 2]]
+                    } else {
+                        [indices[i + 1], indices[i], indices[i + 2]]
+                    }
+                })
+                .collect(),
+        ),
+        Mode::TriangleFan => Some(
+            corners
+                .flat_map(|i| [indices[i + 1], indices[i + 2], indices[0]])
+                .collect(),
+        ),
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => None,
+    }
+}
+
+fn read_material(material: &::gltf::Material<'_>) -> Material {
+    let texture_ref = |info: ::gltf::texture::Info<'_>| TextureRef {
+        image: info.texture().source().index(),
+        texcoord: info.tex_coord(),
+    };
+    let pbr = material.pbr_metallic_roughness();
+    let normal = material.normal_texture();
+    let occlusion = material.occlusion_texture();
+    Material {
+        // only the default material has no name here, and it isn't in `document.materials()`
+        name: material_name(material).unwrap_or_default(),
+        base_color: pbr.base_color_factor().into(),
+        base_color_texture: pbr.base_color_texture().map(texture_ref),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr.metallic_roughness_texture().map(texture_ref),
+        normal_texture: normal.as_ref().map(|normal| TextureRef {
+            image: normal.texture().source().index(),
+            texcoord: normal.tex_coord(),
+        }),
+        normal_scale: normal
+            .as_ref()
+            .map_or(1.0, ::gltf::material::NormalTexture::scale),
+        occlusion_texture: occlusion.as_ref().map(|occlusion| TextureRef {
+            image: occlusion.texture().source().index(),
+            texcoord: occlusion.tex_coord(),
+        }),
+        occlusion_strength: occlusion
+            .as_ref()
+            .map_or(1.0, ::gltf::material::OcclusionTexture::strength),
+        emissive: material.emissive_factor().into(),
+        emissive_texture: material.emissive_texture().map(texture_ref),
+        alpha_mode: match material.alpha_mode() {
+            ::gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            ::gltf::material::AlphaMode::Mask => AlphaMode::Mask {
+                cutoff: material.alpha_cutoff().unwrap_or(0.5),
+            },
+            ::gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        double_sided: material.double_sided(),
+    }
+}
+
+#[cfg(test)]
+// values read from the test buffers are exact
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+
+    // a triangle's three positions, then its indices as u16, 42 bytes
+    fn triangle_buffer() -> Vec<u8> {
+        let positions = [0.0_f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let indices = [0_u16, 2, 1];
+        let mut buffer: Vec<u8> = positions.iter().flat_map(|p| p.to_le_bytes()).collect();
+        buffer.extend(indices.iter().flat_map(|i| i.to_le_bytes()));
+        buffer
+    }
+
+    // a parent node moving a scaled child that holds the triangle, `buffer` is the JSON of
+    // the only buffer without its byte length
+    fn document(buffer: &str) -> String {
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0] }}],
+                "nodes": [
+                    {{ "name": "parent", "translation": [1, 0, 0], "children": [1] }},
+                    {{ "name": "child", "scale": [2, 2, 2], "mesh": 0 }}
+                ],
+                "meshes": [{{
+                    "name": "triangle",
+                    "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "material": 0 }}]
+                }}],
+                "materials": [{{
+                    "name": "red",
+                    "pbrMetallicRoughness": {{
+                        "baseColorFactor": [1, 0, 0, 1],
+                        "metallicFactor": 0.25,
+                        "roughnessFactor": 0.5
+                    }},
+                    "alphaMode": "MASK"
+                }}],
+                "buffers": [{{ {buffer} "byteLength": 42 }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+                ],
+                "accessors": [
+                    {{
+                        "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                        "min": [0, 0, 0], "max": [1, 1, 0]
+                    }},
+                    {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+                ]
+            }}"#
+        )
+    }
+
+    fn check_triangle_scene(scene: &Scene) {
+        assert_eq!(scene.roots, [0]);
+        assert_eq!(scene.nodes[0].children, [1]);
+        assert_eq!(scene.nodes[1].mesh, Some(0));
+        assert_eq!(scene.nodes[1].transform.scale, Vec3::splat(2.0));
+
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.indices, [0, 2, 1]);
+        assert_eq!(mesh.groups[0].name, "triangle");
+        assert_eq!(mesh.groups[0].material.as_deref(), Some("red"));
+
+        let material = &scene.materials[0];
+        assert_eq!(material.base_color, Vec4::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!((material.metallic, material.roughness), (0.25, 0.5));
+        assert_eq!(material.alpha_mode, AlphaMode::Mask { cutoff: 0.5 });
+
+        // the child's scale, then the parent's translation
+        let flattened = scene.flatten();
+        assert_eq!(
+            flattened.positions,
+            [
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(3.0, 0.0, 0.0),
+                Vec3::new(1.0, 2.0, 0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn gltf_with_a_data_uri() {
+        let uri = base64::engine::general_purpose::STANDARD.encode(triangle_buffer());
+        let source = document(&format!(
+            r#""uri": "data:application/octet-stream;base64,{uri}","#
+        ));
+        check_triangle_scene(&parse(source.as_bytes(), Path::new("")).unwrap());
+    }
+
+    #[test]
+    fn glb_with_a_binary_chunk() {
+        fn chunk(glb: &mut Vec<u8>, kind: [u8; 4], mut data: Vec<u8>, padding: u8) {
+            data.resize(data.len().next_multiple_of(4), padding);
+            glb.extend(u32::try_from(data.len()).unwrap().to_le_bytes());
+            glb.extend(kind);
+            glb.extend(data);
+        }
+        let mut glb = Vec::new();
+        chunk(&mut glb, *b"JSON", document("").into_bytes(), b' ');
+        chunk(&mut glb, *b"BIN\0", triangle_buffer(), 0);
+        let length = u32::try_from(12 + glb.len()).unwrap();
+        let header = [*b"glTF", 2_u32.to_le_bytes(), length.to_le_bytes()].concat();
+
+        check_triangle_scene(&parse(&[header, glb].concat(), Path::new("")).unwrap());
+    }
+
+    #[test]
+    fn only_local_files_are_read() {
+        for uri in [
+            "https://example.com/triangle.bin",
+            "file:///triangle.bin",
+            "/triangle.bin",
+            "../triangle.bin",
+            "buffers/../../triangle.bin",
+            "%2E%2E/triangle.bin",
+            r"..\\triangle.bin",
+        ] {
+            let source = document(&format!(r#""uri": "{uri}","#));
+            let error = parse(source.as_bytes(), Path::new("")).unwrap_err();
+            assert!(matches!(error, GltfError::ExternalUri(_)), "{error}");
+        }
+        let source = document(r#""uri": "missing%20triangle.bin","#);
+        let error = parse(source.as_bytes(), Path::new("models")).unwrap_err();
+        assert!(
+            matches!(&error, GltfError::Io { path, .. } if path == Path::new("models/missing triangle.bin")),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn strips_and_fans_become_lists() {
+        let indices = [0, 1, 2, 3, 4];
+        assert_eq!(
+            triangles(Mode::TriangleStrip, &indices).unwrap(),
+            [0, 1, 2, 2, 1, 3, 2, 3, 4]
+        );
+        assert_eq!(
+            triangles(Mode::TriangleFan, &indices).unwrap(),
+            [1, 2, 0, 2, 3, 0, 3, 4, 0]
+        );
+        assert_eq!(triangles(Mode::Lines, &indices), None);
+    }
+}
@@ -0,0 +1,221 @@
+// index buffers in the narrowest type that reaches every vertex, and meshes split into parts
+// that 16 bit indices can address, for OpenGL ES 2 without OES_element_index_uint
+use super::{Group, Mesh};
+use glium::backend::Facade;
+use glium::index::{BufferCreationError, IndexBuffer, IndexBufferAny, PrimitiveType};
+use std::collections::HashMap;
+
+// the most vertices 16 bit indices can reach
+pub const MAX_U16_VERTICES: usize = 1 << 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    // u16 if every index fits, u32 otherwise
+    #[must_use]
+    pub fn new(indices: &[u32]) -> Self {
+        indices
+            .iter()
+            .map(|&index| u16::try_from(index).ok())
+            .collect::<Option<_>>()
+            .map_or_else(|| Self::U32(indices.to_vec()), Self::U16)
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        match self {
+            Self::U16(indices) => indices.len(),
+            Self::U32(indices) => indices.len(),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Uploads the indices to `facade`'s context.
+    ///
+    /// # Errors
+    /// If the context can't create the buffer, `BufferCreationError::IndexTypeNotSupported` for u32
+    /// indices on OpenGL ES 2 without `OES_element_index_uint`.
+    pub fn index_buffer(
+        &self,
+        facade: &impl Facade,
+        primitives: PrimitiveType,
+    ) -> Result<IndexBufferAny, BufferCreationError> {
+        Ok(match self {
+            Self::U16(indices) => IndexBuffer::new(facade, primitives, indices)?.into(),
+            Self::U32(indices) => IndexBuffer::new(facade, primitives, indices)?.into(),
+        })
+    }
+}
+
+impl Mesh {
+    #[must_use]
+    pub fn narrow_indices(&self) -> Indices {
+        Indices::new(&self.indices)
+    }
+
+    /// Splits the mesh into meshes of at most `MAX_U16_VERTICES` vertices each, keeping the
+    /// triangles in order. Vertices used by triangles in more than one part are copied into
+    /// each of them, groups are cut at the parts' boundaries. A mesh that's small enough
+    /// stays in one part.
+    ///
+    /// # Panics
+    /// If an index is out of range.
+    #[must_use]
+    pub fn u16_chunks(&self) -> Vec<Self> {
+        if self.vertex_count() <= MAX_U16_VERTICES {
+            return vec![self.clone()];
+        }
+        let mut chunks = Vec::new();
+        let mut chunk = Self::default();
+        // the chunk's vertex for each of the mesh's
+        let mut vertices: HashMap<u32, u32> = HashMap::new();
+        let mut start = 0;
+        for (number, triangle) in self.indices.chunks_exact(3).enumerate() {
+            let new = triangle
+                .iter()
+                .filter(|index| !vertices.contains_key(index))
+                .count();
+            if vertices.len() + new > MAX_U16_VERTICES {
+                let end = number * 3;
+                chunks.push(self.finish_chunk(std::mem::take(&mut chunk), start..end));
+                vertices.clear();
+                start = end;
+            }
+            for &index in triangle {
+                let vertex = *vertices.entry(index).or_insert_with(|| {
+                    self.copy_vertex(index as usize, &mut chunk);
+                    u32::try_from(chunk.positions.len() - 1).expect("at most 2^16 vertices")
+                });
+                chunk.indices.push(vertex);
+            }
+        }
+        chunks.push(self.finish_chunk(chunk, start..self.indices.len()));
+        chunks
+    }
+
+    fn copy_vertex(&self, index: usize, chunk: &mut Self) {
+        chunk.positions.push(self.positions[index]);
+        if let Some(&normal) = self.normals.get(index) {
+            chunk.normals.push(normal);
+        }
+        if let Some(&texcoord) = self.texcoords.get(index) {
+            chunk.texcoords.push(texcoord);
+        }
+        if let Some(&tangent) = self.tangents.get(index) {
+            chunk.tangents.push(tangent);
+        }
+    }
+
+    // the chunk with the groups overlapping `indices`, the part of the mesh's index list it holds
+    fn finish_chunk(&self, mut chunk: Self, indices: std::ops::Range<usize>) -> Self {
+        chunk.groups = self
+            .groups
+            .iter()
+            .filter_map(|group| {
+                let start = group.indices.start.max(indices.start);
+                let end = group.indices.end.min(indices.end);
+                (start < end).then(|| Group {
+                    indices: start - indices.start..end - indices.start,
+                    ..group.clone()
+                })
+            })
+            .collect();
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrices::Vec3;
+
+    // a strip of `count` triangles along x, each sharing an edge with the next; the coordinates
+    // are exact below 2^24
+    #[allow(clippy::cast_precision_loss)]
+    fn strip(count: u32) -> Mesh {
+        let positions = (0..count + 2)
+            .map(|i| Vec3::new((i / 2) as f32, (i % 2) as f32, 0.0))
+            .collect();
+        let indices: Vec<u32> = (0..count).flat_map(|i| [i, i + 1, i + 2]).collect();
+        Mesh {
+            positions,
+            groups: vec![
+                Group {
+                    name: "first".to_owned(),
+                    material: None,
+                    indices: 0..3,
+                },
+                Group {
+                    name: "rest".to_owned(),
+                    material: None,
+                    indices: 3..indices.len(),
+                },
+            ],
+            indices,
+            ..Mesh::default()
+        }
+    }
+
+    #[test]
+    fn indices_are_u16_only_while_they_fit() {
+        let teapot = Mesh::teapot();
+        assert!(matches!(teapot.narrow_indices(), Indices::U16(_)));
+        assert_eq!(teapot.narrow_indices().len(), teapot.indices.len());
+        assert_eq!(Indices::new(&[0, 65_535]), Indices::U16(vec![0, 65_535]));
+        assert_eq!(Indices::new(&[0, 65_536]), Indices::U32(vec![0, 65_536]));
+        assert!(Indices::new(&[]).is_empty());
+    }
+
+    #[test]
+    fn big_meshes_split_into_u16_addressable_chunks() {
+        let count = 100_000;
+        let mesh = strip(count);
+        let chunks = mesh.u16_chunks();
+        assert_eq!(chunks.len(), 2);
+        for chunk in &chunks {
+            assert!(chunk.vertex_count() <= MAX_U16_VERTICES);
+            assert!(matches!(chunk.narrow_indices(), Indices::U16(_)));
+        }
+
+        // the same triangles in the same order, the shared edge copied into both chunks
+        let triangles: Vec<[Vec3; 3]> = chunks
+            .iter()
+            .flat_map(|chunk| {
+                chunk
+                    .indices
+                    .chunks_exact(3)
+                    .map(|triangle| [0, 1, 2].map(|i| chunk.positions[triangle[i] as usize]))
+            })
+            .collect();
+        let expected: Vec<[Vec3; 3]> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|i| mesh.positions[triangle[i] as usize]))
+            .collect();
+        assert_eq!(triangles, expected);
+        let vertices: usize = chunks.iter().map(Mesh::vertex_count).sum();
+        assert_eq!(vertices, mesh.vertex_count() + 2);
+
+        // the first chunk keeps both groups, the second only the rest
+        assert_eq!(chunks[0].groups.len(), 2);
+        assert_eq!(chunks[0].groups[1].indices.end, chunks[0].indices.len());
+        assert_eq!(chunks[1].groups.len(), 1);
+        assert_eq!(chunks[1].groups[0].name, "rest");
+        assert_eq!(chunks[1].groups[0].indices, 0..chunks[1].indices.len());
+    }
+
+    #[test]
+    fn small_meshes_stay_in_one_chunk() {
+        let teapot = Mesh::teapot();
+        assert_eq!(teapot.u16_chunks(), vec![teapot]);
+        assert_eq!(Mesh::default().u16_chunks(), vec![Mesh::default()]);
+    }
+}
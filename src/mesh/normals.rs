@@ -0,0 +1,309 @@
+// vertex normals computed from the triangles, for meshes that come without them
+//
+// A normal points to the side the triangles wind counterclockwise around, as in OBJ and glTF;
+// the built-in teapot winds the other way, so its triangles need flipping first.
+use super::Mesh;
+use crate::matrices::Vec3;
+use std::collections::HashMap;
+
+// how much each triangle around a vertex counts towards its normal
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Weighting {
+    // big triangles count more, cheap and good for evenly tessellated meshes
+    Area,
+    // the angle of the triangle's corner at the vertex, which doesn't depend on how the
+    // surface around the vertex happens to be split into triangles
+    Angle,
+}
+
+impl Mesh {
+    /// Replaces the normals by ones that smooth over every edge, also between vertices
+    /// that are only separate because their texture coordinates differ.
+    pub fn generate_smooth_normals(&mut self, weighting: Weighting) {
+        self.generate_normals(weighting, std::f32::consts::PI);
+    }
+
+    /// Gives every triangle its triangle's normal, adjacent triangles in the same plane keep
+    /// sharing their vertices.
+    pub fn generate_flat_normals(&mut self) {
+        self.generate_normals(Weighting::Area, 0.0);
+    }
+
+    /// Replaces the normals by the weighted average of the normals of the triangles around
+    /// each position, leaving out triangles that meet the corner's own at more than
+    /// `crease_angle` (radians). Vertices whose corners end up with different normals, along
+    /// the hard edges, are split. Tangents depend on the normals, so they're dropped.
+    ///
+    /// # Panics
+    /// If an index is out of range or the mesh ends up with more than 2^32 vertices.
+    pub fn generate_normals(&mut self, weighting: Weighting, crease_angle: f32) {
+        let triangles: Vec<[usize; 3]> = self
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|corner| triangle[corner] as usize))
+            .collect();
+        let face_normals: Vec<Vec3> = triangles
+            .iter()
+            .map(|&triangle| face_normal(triangle.map(|index| self.positions[index])))
+            .collect();
+
+        // triangles around each position, shared by vertices with other attributes
+        let mut around: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+        for (number, triangle) in triangles.iter().enumerate() {
+            for &index in triangle {
+                let triangles = around
+                    .entry(position_bits(self.positions[index]))
+                    .or_default();
+                if triangles.last() != Some(&number) {
+                    triangles.push(number);
+                }
+            }
+        }
+
+        // a little slack so that triangles in the same plane pass a crease angle of 0
+        let min_cos = crease_angle.cos() - 1e-5;
+        let mut vertices: HashMap<(usize, [u32; 3]), u32> = HashMap::new();
+        let mut split = Self {
+            groups: std::mem::take(&mut self.groups),
+            ..Self::default()
+        };
+        for (number, triangle) in triangles.iter().enumerate() {
+            for &index in triangle {
+                let own = face_normals[number];
+                let normal = around[&position_bits(self.positions[index])]
+                    .iter()
+                    .filter(|&&other| {
+                        // a degenerate triangle has no direction of its own to keep, so it smooths
+                        own == Vec3::ZERO || own.dot(face_normals[other]) >= min_cos
+                    })
+                    .map(|&other| {
+                        let position = self.positions[index];
+                        face_normals[other] * weight(weighting, &triangles[other], position, self)
+                    })
+                    .fold(Vec3::ZERO, |sum, normal| sum + normal);
+                let normal = if normal == Vec3::ZERO {
+                    normal
+                } else {
+                    normal.normalized()
+                };
+
+                let vertex = *vertices
+                    .entry((index, position_bits(normal)))
+                    .or_insert_with(|| {
+                        split.positions.push(self.positions[index]);
+                        split.normals.push(normal);
+                        if let Some(&texcoord) = self.texcoords.get(index) {
+                            split.texcoords.push(texcoord);
+                        }
+                        u32::try_from(split.positions.len() - 1).expect("more than 2^32 vertices")
+                    });
+                split.indices.push(vertex);
+            }
+        }
+        *self = split;
+    }
+}
+
+// the unit normal of a counterclockwise triangle, zero if it has no area
+fn face_normal([a, b, c]: [Vec3; 3]) -> Vec3 {
+    let normal = (b - a).cross(c - a);
+    if normal.length() <= f32::EPSILON {
+        Vec3::ZERO
+    } else {
+        normal.normalized()
+    }
+}
+
+// how much the triangle counts for the normal at its corner at `position`
+fn weight(weighting: Weighting, triangle: &[usize; 3], position: Vec3, mesh: &Mesh) -> f32 {
+    let [a, b, c] = triangle.map(|index| mesh.positions[index]);
+    match weighting {
+        // twice the area, the scale doesn't matter
+        Weighting::Area => (b - a).cross(c - a).length(),
+        Weighting::Angle => {
+            let corner = [a, b, c]
+                .iter()
+                .position(|&corner| corner == position)
+                .unwrap_or_default();
+            let [previous, current, next] =
+                [(corner + 2) % 3, corner, (corner + 1) % 3].map(|i| [a, b, c][i]);
+            let (to_previous, to_next) = (previous - current, next - current);
+            let lengths = to_previous.length() * to_next.length();
+            if lengths <= f32::EPSILON {
+                0.0
+            } else {
+                (to_previous.dot(to_next) / lengths).clamp(-1.0, 1.0).acos()
+            }
+        }
+    }
+}
+
+fn position_bits(position: Vec3) -> [u32; 3] {
+    [position.x, position.y, position.z].map(f32::to_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    // the teapot with counterclockwise triangles and without normals, and the shipped normals
+    fn teapot() -> (Mesh, Vec<Vec3>) {
+        let mut mesh = Mesh::teapot();
+        mesh.flip_winding();
+        let shipped = std::mem::take(&mut mesh.normals);
+        (mesh, shipped)
+    }
+
+    // angles in degrees between generated normals and the shipped ones of the same position
+    fn deviations(generated: &Mesh, shipped: &Mesh) -> Vec<f32> {
+        let shipped: HashMap<[u32; 3], Vec3> = shipped
+            .positions
+            .iter()
+            .zip(&shipped.normals)
+            .map(|(&position, &normal)| (position_bits(position), normal))
+            .collect();
+        generated
+            .positions
+            .iter()
+            .zip(&generated.normals)
+            .map(|(&position, normal)| {
+                let cos = normal
+                    .dot(shipped[&position_bits(position)])
+                    .clamp(-1.0, 1.0);
+                cos.acos().to_degrees()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn angle_weighted_normals_match_the_shipped_teapot_normals() {
+        let (mut mesh, shipped) = teapot();
+        let reference = Mesh {
+            normals: shipped,
+            ..mesh.clone()
+        };
+        mesh.generate_smooth_normals(Weighting::Angle);
+        let deviations = deviations(&mesh, &reference);
+        // one position has two vertices with different normals, a smooth normal matches one
+        let off: Vec<f32> = deviations
+            .into_iter()
+            .filter(|&angle| angle > 0.1)
+            .collect();
+        assert!(off.len() <= 2, "{off:?}");
+    }
+
+    #[test]
+    fn area_weighted_normals_lean_towards_big_triangles() {
+        let (mut mesh, shipped) = teapot();
+        let reference = Mesh {
+            normals: shipped,
+            ..mesh.clone()
+        };
+        mesh.generate_smooth_normals(Weighting::Area);
+        let deviations = deviations(&mesh, &reference);
+        // the patches are split into strips of uneven triangles, the normals still roughly agree
+        let close = deviations.iter().filter(|&&angle| angle < 15.0).count();
+        assert!(close * 10 >= deviations.len() * 9);
+        assert!(deviations.iter().any(|&angle| angle > 1.0));
+    }
+
+    #[test]
+    fn smaller_crease_angles_split_more_vertices() {
+        let (mut smooth, shipped) = teapot();
+        smooth.generate_smooth_normals(Weighting::Angle);
+        assert_eq!(smooth.vertex_count(), shipped.len());
+
+        let mut previous = smooth.vertex_count();
+        for degrees in [90.0_f32, 60.0, 30.0] {
+            let mut creased = teapot().0;
+            creased.generate_normals(Weighting::Angle, degrees.to_radians());
+            assert!(creased.vertex_count() > previous, "{degrees}");
+            previous = creased.vertex_count();
+        }
+
+        let mut flat = teapot().0;
+        flat.generate_flat_normals();
+        for triangle in flat.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| triangle[corner] as usize);
+            let normal = face_normal([a, b, c].map(|index| flat.positions[index]));
+            if normal != Vec3::ZERO {
+                assert!(normal.dot(flat.normals[a]) > 0.9999);
+            }
+        }
+    }
+
+    // how many of the mesh's normals point away from the center of its bounds
+    fn outward(mesh: &Mesh) -> usize {
+        let (min, max) = mesh.bounds().unwrap();
+        let center = (min + max) * 0.5;
+        mesh.positions
+            .iter()
+            .zip(&mesh.normals)
+            .filter(|&(&position, &normal)| normal.dot(position - center) > 0.0)
+            .count()
+    }
+
+    #[test]
+    fn the_clockwise_teapot_gets_outward_normals_between_flips() {
+        let mut bare = Mesh::teapot();
+        bare.normals.clear();
+
+        let mut mesh = bare.clone();
+        mesh.flip_winding();
+        mesh.generate_smooth_normals(Weighting::Angle);
+        mesh.flip_winding();
+        // the handle's and spout's insides and the bottom's middle face the center, the shipped
+        // normals have 420 of 530 facing away from it
+        assert!(
+            outward(&mesh) * 4 >= mesh.vertex_count() * 3,
+            "{}",
+            outward(&mesh)
+        );
+
+        // without the flips they point inside
+        bare.generate_smooth_normals(Weighting::Angle);
+        assert!(
+            outward(&bare) * 4 <= bare.vertex_count(),
+            "{}",
+            outward(&bare)
+        );
+    }
+
+    #[test]
+    fn a_cube_keeps_its_edges_and_corners_share_vertices_per_face() {
+        // two triangles on each of the 6 faces, counterclockwise from outside
+        let positions: Vec<Vec3> = (0..8)
+            .map(|i| {
+                let coordinate = |bit: u32| if i & (1 << bit) == 0 { -1.0 } else { 1.0 };
+                Vec3::new(coordinate(0), coordinate(1), coordinate(2))
+            })
+            .collect();
+        let faces = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let mut cube = Mesh {
+            positions,
+            indices: faces
+                .iter()
+                .flat_map(|&[a, b, c, d]| [a, b, c, a, c, d])
+                .collect(),
+            ..Mesh::default()
+        };
+
+        let mut smooth = cube.clone();
+        smooth.generate_smooth_normals(Weighting::Angle);
+        assert_eq!(smooth.vertex_count(), 8);
+        let corner = smooth.normals[0];
+        assert!((corner - Vec3::splat(-1.0).normalized()).length() < 1e-6);
+
+        cube.generate_normals(Weighting::Angle, TAU / 8.0);
+        assert_eq!(cube.vertex_count(), 24);
+        assert_eq!(cube.normals[0], Vec3::new(0.0, 0.0, -1.0));
+    }
+}
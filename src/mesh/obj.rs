@@ -0,0 +1,648 @@
+// Wavefront OBJ and MTL, the parts that describe static triangle meshes: positions, normals,
+// texture coordinates, polygonal faces, groups and materials. Curves, lines, points and
+// smoothing groups are skipped.
+use super::{Group, Mesh};
+use crate::matrices::Vec3;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// a parsed OBJ file, materials still to be loaded from `material_libraries`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Obj {
+    pub mesh: Mesh,
+    // `mtllib` file names, relative to the OBJ file
+    pub material_libraries: Vec<String>,
+}
+
+// an OBJ file with the materials of its libraries
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Model {
+    pub mesh: Mesh,
+    pub materials: Vec<Material>,
+}
+
+// the MTL properties of a Blinn-Phong material
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub opacity: f32,
+    // `map_Kd`, relative to the MTL file
+    pub diffuse_map: Option<String>,
+}
+
+impl Material {
+    // the defaults MTL gives a material that doesn't set anything
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ambient: Vec3::splat(0.2),
+            diffuse: Vec3::splat(0.8),
+            specular: Vec3::splat(1.0),
+            shininess: 0.0,
+            opacity: 1.0,
+            diffuse_map: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io { path: PathBuf, error: io::Error },
+    // a malformed line, numbered from 1
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "couldn't read {}: {error}", path.display()),
+            Self::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Reads an OBJ file and the material libraries it names, which are looked for next to it.
+///
+/// A missing library is warned about and skipped, and the groups using materials no library
+/// defines then have none.
+///
+/// # Errors
+/// If a file can't be read or is malformed.
+pub fn load(path: impl AsRef<Path>) -> Result<Model, ObjError> {
+    let path = path.as_ref();
+    let mut obj = parse(&read(path)?)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut materials = Vec::new();
+    let mut missing = false;
+    for library in &obj.material_libraries {
+        match read(&directory.join(library)) {
+            Ok(source) => materials.extend(parse_mtl(&source)?),
+            Err(ObjError::Io { path, error }) if error.kind() == io::ErrorKind::NotFound => {
+                eprintln!("{}: no material library {}", path.display(), library);
+                missing = true;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    if missing {
+        for group in &mut obj.mesh.groups {
+            let defined = |name: &String| materials.iter().any(|material| &material.name == name);
+            if !group.material.as_ref().is_some_and(defined) {
+                group.material = None;
+            }
+        }
+    }
+    Ok(Model {
+        mesh: obj.mesh,
+        materials,
+    })
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    std::fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_owned(),
+        error,
+    })
+}
+
+/// Parses the contents of an OBJ file. Faces with more than three corners are triangulated, and
+/// every distinct position/texcoord/normal combination becomes one vertex of the mesh.
+///
+/// # Errors
+/// If a line is malformed or a face refers to a missing element.
+pub fn parse(source: &str) -> Result<Obj, ObjError> {
+    let mut parser = Parser::default();
+    for (number, line) in source.lines().enumerate() {
+        parser.line = number + 1;
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let arguments: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => {
+                let [x, y, z] = parser.floats(&arguments, 3)?;
+                parser.positions.push(Vec3::new(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = parser.floats(&arguments, 3)?;
+                parser.normals.push(Vec3::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = parser.floats(&arguments, 1)?;
+                parser.texcoords.push([u, v]);
+            }
+            "f" => parser.face(&arguments)?,
+            "g" | "o" => {
+                let name = arguments.join(" ");
+                parser.start_group(name, parser.material.clone());
+            }
+            "usemtl" => {
+                let material = Some(arguments.join(" "));
+                parser.start_group(parser.group_name.clone(), material);
+            }
+            "mtllib" => parser
+                .material_libraries
+                .extend(arguments.iter().map(|&library| library.to_owned())),
+            _ => (),
+        }
+    }
+    Ok(parser.finish())
+}
+
+/// Parses the contents of an MTL file.
+///
+/// # Errors
+/// If a line is malformed or sets a property before any `newmtl`.
+pub fn parse_mtl(source: &str) -> Result<Vec<Material>, ObjError> {
+    let mut materials: Vec<Material> = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let line_number = number + 1;
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let arguments: Vec<&str> = tokens.collect();
+        if keyword == "newmtl" {
+            materials.push(Material::new(&arguments.join(" ")));
+            continue;
+        }
+
+        if !matches!(keyword, "Ka" | "Kd" | "Ks" | "Ns" | "d" | "Tr" | "map_Kd") {
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            return Err(ObjError::Parse {
+                line: line_number,
+                message: format!("`{keyword}` before any `newmtl`"),
+            });
+        };
+        let floats = |count| floats::<3>(&arguments, count, line_number);
+        let color = || floats(3).map(|[red, green, blue]| Vec3::new(red, green, blue));
+        match keyword {
+            "Ka" => material.ambient = color()?,
+            "Kd" => material.diffuse = color()?,
+            "Ks" => material.specular = color()?,
+            "Ns" => material.shininess = floats(1)?[0],
+            "d" => material.opacity = floats(1)?[0],
+            "Tr" => material.opacity = 1.0 - floats(1)?[0],
+            // options come before the file name, which is last
+            _ => material.diffuse_map = arguments.last().map(|&map| map.to_owned()),
+        }
+    }
+    Ok(materials)
+}
+
+// the first `N` numbers of a line, at least `required` of them, missing ones are 0
+fn floats<const N: usize>(
+    arguments: &[&str],
+    required: usize,
+    line: usize,
+) -> Result<[f32; N], ObjError> {
+    if arguments.len() < required {
+        return Err(ObjError::Parse {
+            line,
+            message: format!("expected {required} numbers, found {}", arguments.len()),
+        });
+    }
+    let mut values = [0.0; N];
+    for (value, argument) in values.iter_mut().zip(arguments) {
+        *value = argument.parse().map_err(|_| ObjError::Parse {
+            line,
+            message: format!("`{argument}` isn't a number"),
+        })?;
+    }
+    Ok(values)
+}
+
+// indices into the position, texcoord and normal lists that make up one vertex
+type Corner = (usize, Option<usize>, Option<usize>);
+
+#[derive(Default)]
+struct Parser {
+    line: usize,
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    texcoords: Vec<[f32; 2]>,
+    vertices: HashMap<Corner, u32>,
+    corners: Vec<Corner>,
+    indices: Vec<u32>,
+    groups: Vec<Group>,
+    group_name: String,
+    material: Option<String>,
+    material_libraries: Vec<String>,
+}
+
+impl Parser {
+    fn floats<const N: usize>(
+        &self,
+        arguments: &[&str],
+        required: usize,
+    ) -> Result<[f32; N], ObjError> {
+        floats(arguments, required, self.line)
+    }
+
+    const fn error(&self, message: String) -> ObjError {
+        ObjError::Parse {
+            line: self.line,
+            message,
+        }
+    }
+
+    fn start_group(&mut self, name: String, material: Option<String>) {
+        self.close_group();
+        self.group_name = name;
+        self.material = material;
+    }
+
+    // ends the group of the faces read since the last one, if there were any
+    fn close_group(&mut self) {
+        let start = self.groups.last().map_or(0, |group| group.indices.end);
+        if start < self.indices.len() {
+            self.groups.push(Group {
+                name: self.group_name.clone(),
+                material: self.material.clone(),
+                indices: start..self.indices.len(),
+            });
+        }
+    }
+
+    fn face(&mut self, arguments: &[&str]) -> Result<(), ObjError> {
+        if arguments.len() < 3 {
+            return Err(self.error(format!(
+                "a face needs at least 3 corners, found {}",
+                arguments.len()
+            )));
+        }
+        let corners = arguments
+            .iter()
+            .map(|corner| self.corner(corner))
+            .collect::<Result<Vec<_>, _>>()?;
+        let indices: Vec<u32> = corners
+            .into_iter()
+            .map(|corner| self.vertex(corner))
+            .collect();
+
+        let polygon: Vec<Vec3> = indices
+            .iter()
+            .map(|&i| self.positions[self.corners[i as usize].0])
+            .collect();
+        for triangle in triangulate(&polygon) {
+            self.indices.extend(triangle.map(|corner| indices[corner]));
+        }
+        Ok(())
+    }
+
+    // `v`, `v/vt`, `v//vn` or `v/vt/vn`, 1-based or negative to count back from the last one
+    fn corner(&self, corner: &str) -> Result<Corner, ObjError> {
+        let mut parts = corner.split('/');
+        let position = parts.next().unwrap_or_default();
+        let texcoord = parts.next().filter(|part| !part.is_empty());
+        let normal = parts.next().filter(|part| !part.is_empty());
+
+        let position = self.resolve(position, self.positions.len(), "position")?;
+        let texcoord = texcoord
+            .map(|texcoord| self.resolve(texcoord, self.texcoords.len(), "texture coordinate"))
+            .transpose()?;
+        let normal = normal
+            .map(|normal| self.resolve(normal, self.normals.len(), "normal"))
+            .transpose()?;
+        Ok((position, texcoord, normal))
+    }
+
+    fn resolve(&self, index: &str, count: usize, what: &str) -> Result<usize, ObjError> {
+        let index: i64 = index
+            .parse()
+            .map_err(|_| self.error(format!("`{index}` isn't a {what} index")))?;
+        let resolved = match index {
+            1.. => usize::try_from(index - 1).ok(),
+            ..=-1 => usize::try_from(index.unsigned_abs())
+                .ok()
+                .and_then(|back| count.checked_sub(back)),
+            0 => None,
+        };
+        resolved
+            .filter(|&resolved| resolved < count)
+            .ok_or_else(|| self.error(format!("{what} {index} doesn't exist, there are {count}")))
+    }
+
+    fn vertex(&mut self, corner: Corner) -> u32 {
+        *self.vertices.entry(corner).or_insert_with(|| {
+            self.corners.push(corner);
+            u32::try_from(self.corners.len() - 1).expect("more than 2^32 vertices")
+        })
+    }
+
+    fn finish(mut self) -> Obj {
+        self.close_group();
+
+        // the mesh only gets an attribute if some face uses it, corners without one get zeroes
+        let has_texcoords = self.corners.iter().any(|corner| corner.1.is_some());
+        let has_normals = self.corners.iter().any(|corner| corner.2.is_some());
+        let mesh = Mesh {
+            positions: self
+                .corners
+                .iter()
+                .map(|corner| self.positions[corner.0])
+                .collect(),
+            normals: if has_normals {
+                let normal = |corner: &Corner| corner.2.map_or(Vec3::ZERO, |i| self.normals[i]);
+                self.corners.iter().map(normal).collect()
+            } else {
+                Vec::new()
+            },
+            texcoords: if has_texcoords {
+                let texcoord = |corner: &Corner| corner.1.map_or([0.0; 2], |i| self.texcoords[i]);
+                self.corners.iter().map(texcoord).collect()
+            } else {
+                Vec::new()
+            },
+            tangents: Vec::new(),
+            indices: self.indices,
+            groups: self.groups,
+        };
+        Obj {
+            mesh,
+            material_libraries: self.material_libraries,
+        }
+    }
+}
+
+/// Splits a simple polygon into triangles by ear clipping, keeping its winding.
+///
+/// The corners are projected on the plane the polygon is most parallel to, which is exact for
+/// planar polygons. Polygons that can't be clipped (self-intersecting or degenerate) fall back to a fan.
+#[must_use]
+pub fn triangulate(polygon: &[Vec3]) -> Vec<[usize; 3]> {
+    let all: Vec<usize> = (0..polygon.len()).collect();
+    if polygon.len() <= 3 {
+        return fan(&all);
+    }
+
+    // Newell's method, robust for slightly non-planar polygons
+    let normal = (0..polygon.len()).fold(Vec3::ZERO, |normal, i| {
+        normal + polygon[i].cross(polygon[(i + 1) % polygon.len()])
+    });
+    let normal = Vec3::new(normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let project = |point: Vec3| -> [f32; 2] {
+        if normal.z >= normal.x && normal.z >= normal.y {
+            [point.x, point.y]
+        } else if normal.x >= normal.y {
+            [point.y, point.z]
+        } else {
+            [point.z, point.x]
+        }
+    };
+    let points: Vec<[f32; 2]> = polygon.iter().map(|&point| project(point)).collect();
+    // twice the signed area, whose sign tells which way convex corners turn
+    let orientation = (0..points.len())
+        .map(|i| {
+            let (from, to) = (points[i], points[(i + 1) % points.len()]);
+            from[0].mul_add(to[1], -(from[1] * to[0]))
+        })
+        .sum::<f32>()
+        .signum();
+    if orientation == 0.0 {
+        return fan(&all);
+    }
+
+    let mut remaining = all;
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
+    while remaining.len() > 3 {
+        let Some(ear) = (0..remaining.len())
+            .map(|i| corner_at(&remaining, i))
+            .position(|corner| is_ear(&points, &remaining, corner, orientation))
+        else {
+            break;
+        };
+        triangles.push(corner_at(&remaining, ear));
+        remaining.remove(ear);
+    }
+    triangles.extend(fan(&remaining));
+    triangles
+}
+
+fn fan(corners: &[usize]) -> Vec<[usize; 3]> {
+    (1..corners.len().saturating_sub(1))
+        .map(|i| [corners[0], corners[i], corners[i + 1]])
+        .collect()
+}
+
+// the i-th corner of a polygon with its neighbours
+fn corner_at(polygon: &[usize], i: usize) -> [usize; 3] {
+    let len = polygon.len();
+    [
+        polygon[(i + len - 1) % len],
+        polygon[i],
+        polygon[(i + 1) % len],
+    ]
+}
+
+// positive when the path from `from` over `via` to `to` turns left
+fn turn(from: [f32; 2], via: [f32; 2], to: [f32; 2]) -> f32 {
+    (via[0] - from[0]).mul_add(to[1] - from[1], -((via[1] - from[1]) * (to[0] - from[0])))
+}
+
+// a convex corner whose triangle contains no other corner can be cut off
+fn is_ear(points: &[[f32; 2]], remaining: &[usize], corner: [usize; 3], orientation: f32) -> bool {
+    let [previous, current, next] = corner.map(|i| points[i]);
+    turn(previous, current, next) * orientation > 0.0
+        && remaining
+            .iter()
+            .filter(|other| !corner.contains(other))
+            .all(|&other| {
+                let point = points[other];
+                turn(previous, current, point) * orientation < 0.0
+                    || turn(current, next, point) * orientation < 0.0
+                    || turn(next, previous, point) * orientation < 0.0
+            })
+}
+
+#[cfg(test)]
+// parsed numbers are exact
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use crate::teapot;
+
+    #[test]
+    fn quads_become_two_triangles() {
+        let obj = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+        assert_eq!(obj.mesh.indices, [3, 0, 1, 1, 2, 3]);
+        assert!(obj.mesh.normals.is_empty());
+        assert!(obj.mesh.texcoords.is_empty());
+    }
+
+    #[test]
+    fn concave_polygons_are_clipped_not_fanned() {
+        // an L shape, a fan from the first corner would cover the notch
+        let l_shape = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(2.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(1.0, 2.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+        ];
+        let triangles = triangulate(&l_shape);
+        assert_eq!(triangles.len(), 4);
+        let area: f32 = triangles
+            .iter()
+            .map(|&[a, b, c]| (l_shape[b] - l_shape[a]).cross(l_shape[c] - l_shape[a]).z / 2.0)
+            .sum();
+        // all counterclockwise like the polygon, and together exactly its area
+        assert!((area - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn corners_share_vertices_only_when_all_attributes_match() {
+        let source = "
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            vt 0 0
+            vt 1 1
+            vn 0 0 1
+            f 1/1/1 2/1/1 3/1/1
+            f 1/2/1 3/1/1 2/1/1
+        ";
+        let mesh = parse(source).unwrap().mesh;
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices, [0, 1, 2, 3, 2, 1]);
+        assert_eq!(mesh.texcoords[3], [1.0, 1.0]);
+        assert_eq!(mesh.normals, vec![Vec3::Z; 4]);
+    }
+
+    #[test]
+    fn negative_indices_count_back() {
+        let mesh = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n")
+            .unwrap()
+            .mesh;
+        assert_eq!(mesh.positions, [Vec3::ZERO, Vec3::X, Vec3::Y]);
+    }
+
+    #[test]
+    fn groups_split_at_group_and_material_changes() {
+        let source = "
+            mtllib a.mtl b.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            g body
+            usemtl red
+            f 1 2 3
+            f 1 3 2
+            usemtl blue
+            f 1 2 3
+            g lid
+            f 1 2 3
+        ";
+        let obj = parse(source).unwrap();
+        assert_eq!(obj.material_libraries, ["a.mtl", "b.mtl"]);
+        let groups: Vec<_> = obj
+            .mesh
+            .groups
+            .iter()
+            .map(|group| {
+                (
+                    group.name.as_str(),
+                    group.material.as_deref(),
+                    group.indices.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                ("body", Some("red"), 0..6),
+                ("body", Some("blue"), 6..9),
+                ("lid", Some("blue"), 9..12),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let error = parse("v 0 0 0\nv 1 0 0\n\nf 1 2 3\n").unwrap_err();
+        assert!(matches!(error, ObjError::Parse { line: 4, .. }), "{error}");
+        let error = parse("v 0 zero 0\n").unwrap_err();
+        assert!(matches!(error, ObjError::Parse { line: 1, .. }), "{error}");
+    }
+
+    #[test]
+    fn materials_are_read() {
+        let source = "
+            newmtl shiny
+            Kd 0.8 0.5 0.6
+            Ns 16
+            d 0.5
+            map_Kd -bm 1 textures/shiny.png
+            newmtl plain
+        ";
+        let materials = parse_mtl(source).unwrap();
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].diffuse, Vec3::new(0.8, 0.5, 0.6));
+        assert_eq!(materials[0].shininess, 16.0);
+        assert_eq!(materials[0].opacity, 0.5);
+        assert_eq!(
+            materials[0].diffuse_map.as_deref(),
+            Some("textures/shiny.png")
+        );
+        assert_eq!(materials[1], Material::new("plain"));
+    }
+
+    #[test]
+    fn missing_material_libraries_leave_groups_without_materials() {
+        let directory = std::env::temp_dir().join(format!("obj-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("missing.obj");
+        let source = "
+            mtllib missing.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            usemtl red
+            f 1 2 3
+        ";
+        std::fs::write(&path, source).unwrap();
+        let model = load(&path);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let model = model.unwrap();
+        assert!(model.materials.is_empty());
+        assert_eq!(model.mesh.triangle_count(), 1);
+        assert_eq!(model.mesh.groups[0].material, None);
+    }
+
+    #[test]
+    fn teapot_obj_matches_the_built_in_teapot() {
+        let model = load(concat!(env!("CARGO_MANIFEST_DIR"), "/models/teapot.obj")).unwrap();
+        assert_eq!(model.materials.len(), 1);
+        assert_eq!(model.mesh.groups[0].material.as_deref(), Some("teapot"));
+        assert_eq!(model.mesh.triangle_count(), teapot::INDICES.len() / 3);
+
+        // vertices are numbered in the order faces use them, so compare corner by corner
+        for (&loaded, &built_in) in model.mesh.indices.iter().zip(&teapot::INDICES) {
+            let built_in = usize::from(built_in);
+            assert_eq!(
+                model.mesh.positions[loaded as usize],
+                teapot::VERTICES[built_in].position()
+            );
+            assert_eq!(
+                model.mesh.normals[loaded as usize],
+                teapot::NORMALS[built_in].normal()
+            );
+        }
+    }
+}
@@ -0,0 +1,562 @@
+// reordering triangles and vertices for the GPU, without changing what's drawn: triangles for the
+// post-transform vertex cache (Tom Forsyth's "Linear-Speed Vertex Cache Optimisation") and then
+// for less overdraw, vertices for fetch locality
+//
+// Triangles only move within their group, and keep their corners' order, so the winding stays.
+use super::Mesh;
+use crate::matrices::Vec3;
+use std::fmt;
+use std::ops::Range;
+
+// the FIFO cache the statistics simulate, the size of the smaller GPUs'
+pub const CACHE_SIZE: usize = 16;
+
+// the LRU cache Forsyth's scores are tuned for, a bigger one also does well on FIFO caches
+const FORSYTH_CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+// the last triangle's vertices score the same, whichever order they were used in
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CacheStats {
+    // average cache miss ratio, vertex shader runs per triangle: 3 at worst, about 0.5 at best
+    pub acmr: f32,
+    // average transformed vertex ratio, vertex shader runs per vertex: 1 at best
+    pub atvr: f32,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ACMR {:.3}, ATVR {:.3}", self.acmr, self.atvr)
+    }
+}
+
+// what `Mesh::optimize` did to the vertex cache statistics
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Optimization {
+    pub before: CacheStats,
+    pub after: CacheStats,
+}
+
+impl fmt::Display for Optimization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ACMR {:.3} -> {:.3}, ATVR {:.3} -> {:.3}",
+            self.before.acmr, self.after.acmr, self.before.atvr, self.after.atvr
+        )
+    }
+}
+
+impl Mesh {
+    /// Runs the vertex cache, overdraw and vertex fetch optimizations, in that order, and
+    /// reports the vertex cache statistics for a `CACHE_SIZE` FIFO cache before and after.
+    ///
+    /// # Panics
+    /// If an index is out of range.
+    pub fn optimize(&mut self) -> Optimization {
+        let before = self.vertex_cache_stats(CACHE_SIZE);
+        self.optimize_vertex_cache();
+        self.optimize_overdraw(CACHE_SIZE);
+        self.optimize_vertex_fetch();
+        Optimization {
+            before,
+            after: self.vertex_cache_stats(CACHE_SIZE),
+        }
+    }
+
+    /// How often the vertex shader runs when the triangles are drawn in order with a FIFO
+    /// post-transform cache of `cache_size` vertices. Zero for a mesh without triangles.
+    ///
+    /// # Panics
+    /// If an index is out of range.
+    #[must_use]
+    pub fn vertex_cache_stats(&self, cache_size: usize) -> CacheStats {
+        let misses = misses(&self.indices, self.vertex_count(), cache_size);
+        let mut used = vec![false; self.vertex_count()];
+        for &index in &self.indices {
+            used[index as usize] = true;
+        }
+        let used = used.iter().filter(|&&used| used).count();
+        CacheStats {
+            acmr: ratio(misses, self.triangle_count()),
+            atvr: ratio(misses, used),
+        }
+    }
+
+    /// Reorders the triangles of each group so that they reuse the vertices recently
+    /// transformed, greedily picking the triangle whose vertices score best for their place in
+    /// the cache and how many triangles still need them. A group already in a better order for
+    /// a `CACHE_SIZE` FIFO cache, like the teapot's rows of patches, keeps it.
+    ///
+    /// # Panics
+    /// If an index is out of range.
+    pub fn optimize_vertex_cache(&mut self) {
+        for segment in self.segments() {
+            let indices = &self.indices[segment.clone()];
+            let reordered = forsyth(indices, self.vertex_count());
+            if misses(&reordered, self.vertex_count(), CACHE_SIZE)
+                < misses(indices, self.vertex_count(), CACHE_SIZE)
+            {
+                self.indices[segment].copy_from_slice(&reordered);
+            }
+        }
+    }
+
+    /// Cuts each group's triangles into clusters where the cache starts over, at triangles
+    /// with none of their vertices in a `cache_size` FIFO cache, and draws the clusters on the
+    /// outside facing outwards first, so that they hide what's behind them. Clusters keep their
+    /// triangles' order, so after `optimize_vertex_cache` the cache statistics barely change.
+    ///
+    /// # Panics
+    /// If an index is out of range.
+    pub fn optimize_overdraw(&mut self, cache_size: usize) {
+        for segment in self.segments() {
+            let indices = &self.indices[segment.clone()];
+            let mut clusters: Vec<(f32, Range<usize>)> =
+                clusters(indices, self.vertex_count(), cache_size)
+                    .into_iter()
+                    .map(|cluster| (0.0, cluster))
+                    .collect();
+            let centroids: Vec<(Vec3, f32)> = clusters
+                .iter()
+                .map(|(_, cluster)| self.centroid(&indices[cluster.clone()]))
+                .collect();
+            let total_area: f32 = centroids.iter().map(|&(_, area)| area).sum();
+            if total_area <= 0.0 {
+                continue;
+            }
+            let center = centroids
+                .iter()
+                .fold(Vec3::ZERO, |sum, &(centroid, area)| sum + centroid * area)
+                * total_area.recip();
+            for ((key, cluster), &(centroid, _)) in clusters.iter_mut().zip(&centroids) {
+                *key = (centroid - center).dot(self.cluster_normal(&indices[cluster.clone()]));
+            }
+            clusters.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+            let reordered: Vec<u32> = clusters
+                .iter()
+                .flat_map(|(_, cluster)| indices[cluster.clone()].iter().copied())
+                .collect();
+            self.indices[segment].copy_from_slice(&reordered);
+        }
+    }
+
+    /// Numbers the vertices in the order the triangles first use them, so that the vertex
+    /// shader reads the buffers front to back. Unused vertices go to the end.
+    ///
+    /// # Panics
+    /// If an index is out of range.
+    pub fn optimize_vertex_fetch(&mut self) {
+        let mut new_index: Vec<Option<u32>> = vec![None; self.vertex_count()];
+        let mut order: Vec<usize> = Vec::with_capacity(self.vertex_count());
+        for index in &mut self.indices {
+            let vertex = *index as usize;
+            *index = *new_index[vertex].get_or_insert_with(|| {
+                order.push(vertex);
+                u32::try_from(order.len() - 1).expect("more than 2^32 vertices")
+            });
+        }
+        order.extend((0..self.vertex_count()).filter(|&vertex| new_index[vertex].is_none()));
+
+        self.positions = reorder(&self.positions, &order);
+        self.normals = reorder(&self.normals, &order);
+        self.texcoords = reorder(&self.texcoords, &order);
+        self.tangents = reorder(&self.tangents, &order);
+    }
+
+    // ranges of the index list that triangles can move within: the groups, and the stretches
+    // that no group starts or ends in
+    fn segments(&self) -> Vec<Range<usize>> {
+        let mut boundaries = vec![0, self.indices.len()];
+        for group in &self.groups {
+            boundaries.push(group.indices.start.min(self.indices.len()));
+            boundaries.push(group.indices.end.min(self.indices.len()));
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        boundaries
+            .windows(2)
+            .map(|boundaries| boundaries[0]..boundaries[1])
+            .collect()
+    }
+
+    // the area-weighted center of the triangles, and their area
+    fn centroid(&self, indices: &[u32]) -> (Vec3, f32) {
+        let (sum, area) = indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|corner| self.positions[triangle[corner] as usize]);
+                let area = (b - a).cross(c - a).length() * 0.5;
+                ((a + b + c) * (area / 3.0), area)
+            })
+            .fold((Vec3::ZERO, 0.0), |(sum, total), (centroid, area)| {
+                (sum + centroid, total + area)
+            });
+        if area > 0.0 {
+            (sum * area.recip(), area)
+        } else {
+            (Vec3::ZERO, 0.0)
+        }
+    }
+
+    // which way the triangles face, from the vertex normals, so whichever the winding, or from
+    // the triangles in the teapot's clockwise winding for a mesh without normals; not normalized
+    fn cluster_normal(&self, indices: &[u32]) -> Vec3 {
+        if self.normals.is_empty() {
+            indices
+                .chunks_exact(3)
+                .map(|triangle| {
+                    let [a, b, c] =
+                        [0, 1, 2].map(|corner| self.positions[triangle[corner] as usize]);
+                    (c - a).cross(b - a)
+                })
+                .fold(Vec3::ZERO, |sum, normal| sum + normal)
+        } else {
+            indices
+                .iter()
+                .map(|&index| self.normals[index as usize])
+                .fold(Vec3::ZERO, |sum, normal| sum + normal)
+        }
+    }
+}
+
+// a post-transform cache that evicts the vertex that went in first
+struct Fifo {
+    // when each vertex went in, vertices that went in more than `size` misses ago are out
+    entered: Vec<usize>,
+    misses: usize,
+    size: usize,
+}
+
+impl Fifo {
+    fn new(vertex_count: usize, size: usize) -> Self {
+        Self {
+            // 0 is long enough ago for any vertex to be out
+            entered: vec![0; vertex_count],
+            misses: size + 1,
+            size,
+        }
+    }
+
+    fn miss(&mut self, index: u32) -> bool {
+        let entered = &mut self.entered[index as usize];
+        if self.misses - *entered <= self.size {
+            return false;
+        }
+        *entered = self.misses;
+        self.misses += 1;
+        true
+    }
+}
+
+// how many vertices drawing `indices` transforms with a FIFO cache
+fn misses(indices: &[u32], vertex_count: usize, cache_size: usize) -> usize {
+    let mut cache = Fifo::new(vertex_count, cache_size);
+    indices.iter().filter(|&&index| cache.miss(index)).count()
+}
+
+// the triangles in `indices` reordered by Forsyth's algorithm
+fn forsyth(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangles: Vec<[usize; 3]> = indices
+        .chunks_exact(3)
+        .map(|triangle| [0, 1, 2].map(|corner| triangle[corner] as usize))
+        .collect();
+
+    // the triangles around each vertex, `around[starts[v]..starts[v + 1]]`
+    let mut remaining = vec![0_u32; vertex_count];
+    for &vertex in triangles.iter().flatten() {
+        remaining[vertex] += 1;
+    }
+    let mut starts = vec![0; vertex_count + 1];
+    for vertex in 0..vertex_count {
+        starts[vertex + 1] = starts[vertex] + remaining[vertex] as usize;
+    }
+    let mut around = vec![0; starts[vertex_count]];
+    let mut filled = starts.clone();
+    for (number, triangle) in triangles.iter().enumerate() {
+        for &vertex in triangle {
+            around[filled[vertex]] = number;
+            filled[vertex] += 1;
+        }
+    }
+
+    let mut cache_positions: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_scores: Vec<f32> = remaining
+        .iter()
+        .map(|&remaining| vertex_score(None, remaining))
+        .collect();
+    let mut drawn = vec![false; triangles.len()];
+    // where to continue when no triangle around the cache is left, in the original order
+    let mut next_undrawn = 0;
+    let mut best = None;
+    // vertices pushed out by the last triangle are kept at the end for updating their scores
+    let mut cache: Vec<usize> = Vec::with_capacity(FORSYTH_CACHE_SIZE + 3);
+    let mut reordered = Vec::with_capacity(indices.len());
+
+    for _ in 0..triangles.len() {
+        let triangle = best.unwrap_or_else(|| {
+            while drawn[next_undrawn] {
+                next_undrawn += 1;
+            }
+            next_undrawn
+        });
+        drawn[triangle] = true;
+        reordered.extend_from_slice(&indices[triangle * 3..triangle * 3 + 3]);
+
+        let corners = triangles[triangle];
+        for &vertex in &corners {
+            remaining[vertex] -= 1;
+        }
+        cache.retain(|vertex| !corners.contains(vertex));
+        cache.splice(0..0, corners);
+        for (position, &vertex) in cache.iter().enumerate() {
+            cache_positions[vertex] = (position < FORSYTH_CACHE_SIZE).then_some(position);
+            vertex_scores[vertex] = vertex_score(cache_positions[vertex], remaining[vertex]);
+        }
+
+        best = None;
+        let mut best_score = f32::NEG_INFINITY;
+        for &vertex in &cache {
+            for &other in &around[starts[vertex]..starts[vertex + 1]] {
+                if drawn[other] {
+                    continue;
+                }
+                let score: f32 = triangles[other]
+                    .iter()
+                    .map(|&corner| vertex_scores[corner])
+                    .sum();
+                if score > best_score {
+                    best_score = score;
+                    best = Some(other);
+                }
+            }
+        }
+        cache.truncate(FORSYTH_CACHE_SIZE);
+    }
+    reordered
+}
+
+// how much drawing a triangle with the vertex next is worth
+#[allow(clippy::cast_precision_loss)] // cache positions and triangle counts, exact in f32
+fn vertex_score(cache_position: Option<usize>, remaining: u32) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        None => 0.0,
+        Some(position) if position < 3 => LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            (1.0 - (position - 3) as f32 / (FORSYTH_CACHE_SIZE - 3) as f32).powf(CACHE_DECAY_POWER)
+        }
+    };
+    // vertices with few triangles left are worth finishing off
+    VALENCE_BOOST_SCALE.mul_add((remaining as f32).powf(-VALENCE_BOOST_POWER), cache_score)
+}
+
+// triangle ranges, as index ranges, that each start with a triangle none of whose vertices
+// are in the cache
+fn clusters(indices: &[u32], vertex_count: usize, cache_size: usize) -> Vec<Range<usize>> {
+    let mut cache = Fifo::new(vertex_count, cache_size);
+    let mut starts: Vec<usize> = indices
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(_, triangle)| {
+            let misses = triangle.iter().filter(|&&index| cache.miss(index)).count();
+            misses == 3
+        })
+        .map(|(number, _)| number * 3)
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts.push(indices.len());
+    starts.dedup();
+    starts.windows(2).map(|range| range[0]..range[1]).collect()
+}
+
+fn reorder<T: Copy>(attribute: &[T], order: &[usize]) -> Vec<T> {
+    if attribute.is_empty() {
+        return Vec::new();
+    }
+    order.iter().map(|&index| attribute[index]).collect()
+}
+
+// 0 for nothing to divide by
+#[allow(clippy::cast_precision_loss)] // ratios don't need more than f32's precision
+fn ratio(count: usize, of: usize) -> f32 {
+    if of == 0 {
+        0.0
+    } else {
+        count as f32 / of as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Group;
+
+    // each group's triangles as positions, each turned to start at its smallest corner and
+    // sorted, so the same for the same triangles in any order
+    fn triangles_by_group(mesh: &Mesh) -> Vec<Vec<[[u32; 3]; 3]>> {
+        mesh.groups
+            .iter()
+            .map(|group| {
+                let mut triangles: Vec<[[u32; 3]; 3]> = mesh.indices[group.indices.clone()]
+                    .chunks_exact(3)
+                    .map(|triangle| {
+                        let corners = [0, 1, 2].map(|corner| {
+                            let p = mesh.positions[triangle[corner] as usize];
+                            [p.x, p.y, p.z].map(f32::to_bits)
+                        });
+                        let first = (0..3).min_by_key(|&i| corners[i]).unwrap_or_default();
+                        [0, 1, 2].map(|i| corners[(first + i) % 3])
+                    })
+                    .collect();
+                triangles.sort_unstable();
+                triangles
+            })
+            .collect()
+    }
+
+    // the teapot twice, side by side, each in its own group
+    fn two_teapots() -> Mesh {
+        let mut mesh = Mesh::teapot();
+        let mut other = Mesh::teapot();
+        other.transform(&crate::matrices::Mat4::translation(10.0, 0.0, 0.0));
+        other.groups[0].name = "other".to_owned();
+        mesh.append(&other);
+        mesh
+    }
+
+    #[test]
+    fn cache_stats_count_vertex_shader_runs() {
+        let mesh = |indices: Vec<u32>| Mesh {
+            positions: vec![Vec3::ZERO; 64],
+            indices,
+            ..Mesh::default()
+        };
+        let single = mesh(vec![0, 1, 2]).vertex_cache_stats(CACHE_SIZE);
+        assert_eq!(
+            single,
+            CacheStats {
+                acmr: 3.0,
+                atvr: 1.0
+            }
+        );
+
+        // a strip transforms every vertex once, two more than it has triangles
+        let strip: Vec<u32> = (0..40).flat_map(|i| [i, i + 1, i + 2]).collect();
+        let stats = mesh(strip).vertex_cache_stats(CACHE_SIZE);
+        assert!((stats.acmr - 42.0 / 40.0).abs() < 1e-6);
+        assert!((stats.atvr - 1.0).abs() < 1e-6);
+
+        // a vertex used again after 16 others is transformed again
+        let mut indices: Vec<u32> = (0..18).collect();
+        indices.extend([0, 1, 2]);
+        let stats = mesh(indices).vertex_cache_stats(CACHE_SIZE);
+        assert!((stats.acmr - 3.0).abs() < 1e-6, "{stats}");
+        let empty = mesh(Vec::new()).vertex_cache_stats(CACHE_SIZE);
+        assert!(empty.acmr.abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn optimizing_scrambled_triangles_transforms_fewer_vertices() {
+        // every 7th triangle in turn, far from where the last one left the cache
+        let teapot = Mesh::teapot();
+        let triangles: Vec<&[u32]> = teapot.indices.chunks_exact(3).collect();
+        let mut scrambled = teapot.clone();
+        scrambled.indices = (0..triangles.len())
+            .flat_map(|i| triangles[i * 7 % triangles.len()].iter().copied())
+            .collect();
+
+        let optimization = scrambled.optimize();
+        assert!(optimization.before.acmr > 2.0, "{optimization}");
+        assert!(optimization.after.acmr < 0.8, "{optimization}");
+        assert!(optimization.after.atvr < 1.5, "{optimization}");
+        assert_eq!(triangles_by_group(&scrambled), triangles_by_group(&teapot));
+    }
+
+    #[test]
+    fn the_teapot_order_is_kept_when_it_is_better() {
+        let mut teapot = Mesh::teapot();
+        let optimization = teapot.optimize();
+        assert!(
+            optimization.after.acmr <= optimization.before.acmr,
+            "{optimization}"
+        );
+    }
+
+    #[test]
+    fn triangles_stay_in_their_groups() {
+        let original = two_teapots();
+        let mut mesh = original.clone();
+        mesh.optimize();
+        assert_eq!(mesh.groups, original.groups);
+        assert_eq!(triangles_by_group(&mesh), triangles_by_group(&original));
+        assert_eq!(mesh.vertex_count(), original.vertex_count());
+    }
+
+    #[test]
+    fn vertices_are_numbered_in_order_of_first_use() {
+        let mut mesh = Mesh::teapot();
+        mesh.positions.push(Vec3::splat(5.0));
+        mesh.normals.push(Vec3::Y);
+        mesh.optimize();
+        let mut next = 0;
+        for &index in &mesh.indices {
+            assert!(index <= next);
+            if index == next {
+                next += 1;
+            }
+        }
+        // the unused vertex, at the end
+        assert_eq!(next as usize, mesh.vertex_count() - 1);
+        assert_eq!(mesh.positions[next as usize], Vec3::splat(5.0));
+        assert_eq!(mesh.normals[next as usize], Vec3::Y);
+    }
+
+    #[test]
+    fn outward_clusters_are_drawn_first() {
+        let (mut mesh, front_indices) = two_quads();
+        mesh.optimize_overdraw(CACHE_SIZE);
+        assert_eq!(&mesh.indices[..6], &front_indices);
+    }
+
+    #[test]
+    fn clusters_without_normals_face_the_clockwise_side() {
+        let (mut mesh, front_indices) = two_quads();
+        mesh.normals.clear();
+        mesh.optimize_overdraw(CACHE_SIZE);
+        assert_eq!(&mesh.indices[..6], &front_indices);
+    }
+
+    // two far apart quads facing +z, clockwise seen from there, and the indices of the one further
+    // along +z, which is in front of the other
+    fn two_quads() -> (Mesh, [u32; 6]) {
+        let quad = |z: f32, first: u32| {
+            (
+                [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].map(|[x, y]| Vec3::new(x, y, z)),
+                [0, 2, 1, 0, 3, 2].map(|i| first + i),
+            )
+        };
+        let (back, back_indices) = quad(0.0, 0);
+        let (front, front_indices) = quad(1.0, 4);
+        let mesh = Mesh {
+            positions: back.into_iter().chain(front).collect(),
+            normals: vec![Vec3::Z; 8],
+            indices: back_indices.into_iter().chain(front_indices).collect(),
+            groups: vec![Group {
+                name: "quads".to_owned(),
+                material: None,
+                indices: 0..12,
+            }],
+            ..Mesh::default()
+        };
+        (mesh, front_indices)
+    }
+}
@@ -0,0 +1,189 @@
+// per-vertex tangents for normal mapping, computed with MikkTSpace so that they match the
+// tangent space normal maps are baked in by Blender, Substance and the glTF sample models
+use super::Mesh;
+use crate::matrices::Vec4;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TangentError {
+    MissingNormals,
+    MissingTexcoords,
+    // MikkTSpace gave up on the mesh, which happens when it has no triangles
+    Failed,
+}
+
+impl fmt::Display for TangentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingNormals => write!(f, "tangents need normals"),
+            Self::MissingTexcoords => write!(f, "tangents need texture coordinates"),
+            Self::Failed => write!(f, "couldn't generate tangents"),
+        }
+    }
+}
+
+impl std::error::Error for TangentError {}
+
+impl Mesh {
+    /// Replaces the tangents by `MikkTSpace`'s. It works on triangle corners, so a vertex whose
+    /// corners get different tangents, along a UV mirror seam for example, is split.
+    ///
+    /// # Errors
+    /// If the mesh has no normals or texture coordinates, or no triangles.
+    ///
+    /// # Panics
+    /// If an index is out of range or the mesh ends up with more than 2^32 vertices.
+    pub fn generate_tangents(&mut self) -> Result<(), TangentError> {
+        if self.normals.is_empty() {
+            return Err(TangentError::MissingNormals);
+        }
+        if self.texcoords.is_empty() {
+            return Err(TangentError::MissingTexcoords);
+        }
+        let mut corners = Corners {
+            mesh: self,
+            tangents: vec![Vec4::ZERO; self.indices.len()],
+        };
+        if !bevy_mikktspace::generate_tangents(&mut corners) {
+            return Err(TangentError::Failed);
+        }
+        let tangents = corners.tangents;
+
+        let mut vertices: HashMap<(u32, [u32; 4]), u32> = HashMap::new();
+        let mut split = Self {
+            groups: std::mem::take(&mut self.groups),
+            ..Self::default()
+        };
+        for (&index, tangent) in self.indices.iter().zip(tangents) {
+            let bits = [tangent.x, tangent.y, tangent.z, tangent.w].map(f32::to_bits);
+            let vertex = *vertices.entry((index, bits)).or_insert_with(|| {
+                let index = index as usize;
+                split.positions.push(self.positions[index]);
+                split.normals.push(self.normals[index]);
+                split.texcoords.push(self.texcoords[index]);
+                split.tangents.push(tangent);
+                u32::try_from(split.positions.len() - 1).expect("more than 2^32 vertices")
+            });
+            split.indices.push(vertex);
+        }
+        *self = split;
+        Ok(())
+    }
+}
+
+// the mesh as MikkTSpace sees it, with room for a tangent per triangle corner
+struct Corners<'a> {
+    mesh: &'a Mesh,
+    tangents: Vec<Vec4>,
+}
+
+impl Corners<'_> {
+    fn vertex(&self, face: usize, corner: usize) -> usize {
+        self.mesh.indices[face * 3 + corner] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for Corners<'_> {
+    fn num_faces(&self) -> usize {
+        self.mesh.triangle_count()
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, corner: usize) -> [f32; 3] {
+        self.mesh.positions[self.vertex(face, corner)].into()
+    }
+
+    fn normal(&self, face: usize, corner: usize) -> [f32; 3] {
+        self.mesh.normals[self.vertex(face, corner)].into()
+    }
+
+    fn tex_coord(&self, face: usize, corner: usize) -> [f32; 2] {
+        self.mesh.texcoords[self.vertex(face, corner)]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, corner: usize) {
+        self.tangents[face * 3 + corner] = tangent.into();
+    }
+}
+
+#[cfg(test)]
+// a flat, axis-aligned quad has exact tangents
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use crate::matrices::Vec3;
+
+    // a unit quad in the xy plane facing +z, u along `u_axis` and v along `v_axis`
+    fn quad(u_axis: Vec3, v_axis: Vec3) -> Mesh {
+        let corners = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        Mesh {
+            positions: corners
+                .iter()
+                .map(|&[u, v]| u_axis * u + v_axis * v)
+                .collect(),
+            normals: vec![Vec3::Z; 4],
+            texcoords: corners.to_vec(),
+            indices: if u_axis.cross(v_axis).z > 0.0 {
+                vec![0, 1, 2, 0, 2, 3]
+            } else {
+                vec![0, 2, 1, 0, 3, 2]
+            },
+            ..Mesh::default()
+        }
+    }
+
+    #[test]
+    fn tangents_follow_u_and_bitangents_v() {
+        let mut mesh = quad(Vec3::X, Vec3::Y);
+        mesh.generate_tangents().unwrap();
+        assert_eq!(mesh.vertex_count(), 4);
+        for &tangent in &mesh.tangents {
+            assert!((tangent.truncate() - Vec3::X).length() < 1e-6);
+            assert_eq!(tangent.w, 1.0);
+        }
+
+        // mirrored texture coordinates flip the bitangent, not the normal
+        let mut mirrored = quad(Vec3::X, -Vec3::Y);
+        mirrored.generate_tangents().unwrap();
+        for &tangent in &mirrored.tangents {
+            assert!((tangent.truncate() - Vec3::X).length() < 1e-6);
+            assert_eq!(tangent.w, -1.0);
+        }
+    }
+
+    #[test]
+    fn a_mirror_seam_splits_the_vertices_on_it() {
+        // two quads sharing the edge x = 1, the second one's u running back from it
+        let mut mesh = quad(Vec3::X, Vec3::Y);
+        mesh.positions
+            .extend([Vec3::new(2.0, 0.0, 0.0), Vec3::new(2.0, 1.0, 0.0)]);
+        mesh.normals.extend([Vec3::Z; 2]);
+        mesh.texcoords.extend([[0.0, 0.0], [0.0, 1.0]]);
+        mesh.indices.extend([1, 4, 5, 1, 5, 2]);
+
+        mesh.generate_tangents().unwrap();
+        assert_eq!(mesh.vertex_count(), 8);
+        let along = |direction: Vec3| {
+            mesh.tangents
+                .iter()
+                .filter(|tangent| (tangent.truncate() - direction).length() < 1e-6)
+                .count()
+        };
+        assert_eq!((along(Vec3::X), along(-Vec3::X)), (4, 4));
+    }
+
+    #[test]
+    fn the_teapot_has_no_texture_coordinates_for_tangents() {
+        assert_eq!(
+            Mesh::teapot().generate_tangents(),
+            Err(TangentError::MissingTexcoords)
+        );
+        let mut bare = Mesh::teapot();
+        bare.normals.clear();
+        assert_eq!(bare.generate_tangents(), Err(TangentError::MissingNormals));
+    }
+}
@@ -0,0 +1,420 @@
+// checks for the problems meshes tend to come with, and fixes for the ones that can be fixed
+// without guessing: degenerate triangles, duplicate and unused vertices
+use super::Mesh;
+use crate::matrices::{Vec3, Vec4};
+use std::collections::HashMap;
+use std::fmt;
+
+// what `Mesh::validate` found, everything empty for a clean mesh
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    // positions in `Mesh::indices` of indices past the last vertex
+    pub out_of_range_indices: Vec<usize>,
+    // triangles, numbered like `Mesh::indices` in threes, with a repeated corner or no area
+    pub degenerate_triangles: Vec<usize>,
+    pub unused_vertices: Vec<u32>,
+    // vertex pairs, the lower one first, that are an edge of more than two triangles
+    pub non_manifold_edges: Vec<[u32; 2]>,
+    pub zero_length_normals: Vec<u32>,
+}
+
+impl Report {
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.out_of_range_indices.is_empty()
+            && self.degenerate_triangles.is_empty()
+            && self.unused_vertices.is_empty()
+            && self.non_manifold_edges.is_empty()
+            && self.zero_length_normals.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return write!(f, "no problems");
+        }
+        let counts = [
+            (self.out_of_range_indices.len(), "out of range indices"),
+            (self.degenerate_triangles.len(), "degenerate triangles"),
+            (self.unused_vertices.len(), "unused vertices"),
+            (self.non_manifold_edges.len(), "non-manifold edges"),
+            (self.zero_length_normals.len(), "zero length normals"),
+        ];
+        let problems: Vec<String> = counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, problem)| format!("{count} {problem}"))
+            .collect();
+        write!(f, "{}", problems.join(", "))
+    }
+}
+
+// what `Mesh::repair` changed
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Repairs {
+    pub removed_triangles: usize,
+    pub merged_vertices: usize,
+    pub removed_vertices: usize,
+}
+
+// a triangle whose cross product is this small relative to its longest edge squared has no area
+const DEGENERATE_AREA: f32 = 1e-6;
+
+impl Mesh {
+    #[must_use]
+    pub fn validate(&self) -> Report {
+        let vertex_count = self.positions.len();
+        let mut report = Report {
+            out_of_range_indices: (0..self.indices.len())
+                .filter(|&i| self.indices[i] as usize >= vertex_count)
+                .collect(),
+            ..Report::default()
+        };
+
+        let mut used = vec![false; vertex_count];
+        let mut edges: HashMap<[u32; 2], usize> = HashMap::new();
+        for (number, triangle) in self.triangles() {
+            // already reported, and nothing else can be said about them
+            if triangle.iter().any(|&index| index as usize >= vertex_count) {
+                continue;
+            }
+            for index in triangle {
+                used[index as usize] = true;
+            }
+            if self.is_degenerate(triangle) {
+                report.degenerate_triangles.push(number);
+                continue;
+            }
+            for corner in 0..3 {
+                let (from, to) = (triangle[corner], triangle[(corner + 1) % 3]);
+                *edges.entry([from.min(to), from.max(to)]).or_default() += 1;
+            }
+        }
+
+        report.unused_vertices = (0..vertex_count)
+            .filter(|&index| !used[index])
+            .map(vertex_index)
+            .collect();
+        report.non_manifold_edges = edges
+            .into_iter()
+            .filter(|&(_, count)| count > 2)
+            .map(|(edge, _)| edge)
+            .collect();
+        report.non_manifold_edges.sort_unstable();
+        report.zero_length_normals = (0..self.normals.len())
+            .filter(|&index| self.normals[index].length() <= f32::EPSILON)
+            .map(vertex_index)
+            .collect();
+        report
+    }
+
+    /// Fixes what can be fixed: drops triangles with an index out of range or no area, merges
+    /// vertices whose attributes are all equal and drops the ones no triangle uses.
+    /// Non-manifold edges and zero length normals are left alone.
+    ///
+    /// # Panics
+    /// If the mesh has more than 2^32 vertices.
+    pub fn repair(&mut self) -> Repairs {
+        let vertex_count = self.positions.len();
+        let in_range = |triangle: [u32; 3]| triangle.iter().all(|&i| (i as usize) < vertex_count);
+        let removed_triangles =
+            self.retain_triangles(in_range) + self.remove_degenerate_triangles();
+        let merged_vertices = self.merge_duplicate_vertices();
+        Repairs {
+            removed_triangles,
+            merged_vertices,
+            removed_vertices: self.remove_unused_vertices(),
+        }
+    }
+
+    /// Drops triangles with a repeated corner or no area, returns how many.
+    ///
+    /// # Panics
+    /// If an index is out of range.
+    pub fn remove_degenerate_triangles(&mut self) -> usize {
+        let degenerate: Vec<bool> = self
+            .triangles()
+            .map(|(_, triangle)| self.is_degenerate(triangle))
+            .collect();
+        let mut number = 0;
+        self.retain_triangles(|_| {
+            number += 1;
+            !degenerate[number - 1]
+        })
+    }
+
+    /// Makes vertices with exactly the same position, normal, texture coordinates and tangent one
+    /// vertex, returns how many were merged into another. The duplicates are left unused.
+    ///
+    /// # Panics
+    /// If an index is out of range or the mesh has more than 2^32 vertices.
+    pub fn merge_duplicate_vertices(&mut self) -> usize {
+        let mut first_of: HashMap<[u32; 12], u32> = HashMap::new();
+        let mut replacement = Vec::with_capacity(self.positions.len());
+        for index in 0..self.positions.len() {
+            let first = *first_of
+                .entry(self.vertex_bits(index))
+                .or_insert_with(|| vertex_index(index));
+            replacement.push(first);
+        }
+        for index in &mut self.indices {
+            *index = replacement[*index as usize];
+        }
+        self.positions.len() - first_of.len()
+    }
+
+    /// Drops the vertices no triangle uses and renumbers the rest in order, returns how many
+    /// were dropped.
+    ///
+    /// # Panics
+    /// If an index is out of range or the mesh has more than 2^32 vertices.
+    pub fn remove_unused_vertices(&mut self) -> usize {
+        let mut used = vec![false; self.positions.len()];
+        for &index in &self.indices {
+            used[index as usize] = true;
+        }
+        let mut new_index = vec![0; self.positions.len()];
+        let mut kept = 0;
+        for (index, &used) in used.iter().enumerate() {
+            if used {
+                new_index[index] = kept;
+                kept += 1;
+            }
+        }
+        for index in &mut self.indices {
+            *index = new_index[*index as usize];
+        }
+
+        let removed = self.positions.len() - kept as usize;
+        retain_used(&mut self.positions, &used);
+        retain_used(&mut self.normals, &used);
+        retain_used(&mut self.texcoords, &used);
+        retain_used(&mut self.tangents, &used);
+        removed
+    }
+
+    // complete triangles with their number
+    fn triangles(&self) -> impl Iterator<Item = (usize, [u32; 3])> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .enumerate()
+    }
+
+    fn is_degenerate(&self, triangle: [u32; 3]) -> bool {
+        let [a, b, c] = triangle;
+        if a == b || b == c || c == a {
+            return true;
+        }
+        let [a, b, c] = triangle.map(|index| self.positions[index as usize]);
+        let longest = [b - a, c - b, a - c]
+            .iter()
+            .map(|edge| edge.dot(*edge))
+            .fold(0.0, f32::max);
+        (b - a).cross(c - a).length() <= DEGENERATE_AREA * longest
+    }
+
+    // keeps the triangles `keep` accepts, in order, and shrinks the groups to match;
+    // returns how many were dropped
+    fn retain_triangles(&mut self, mut keep: impl FnMut([u32; 3]) -> bool) -> usize {
+        let mut indices = Vec::with_capacity(self.indices.len());
+        let mut groups = Vec::with_capacity(self.groups.len());
+        let mut group_index = 0;
+        let mut group_start = 0;
+        for (number, triangle) in self.triangles() {
+            // close the groups that end before this triangle
+            while let Some(group) = self.groups.get(group_index) {
+                if group.indices.end > number * 3 {
+                    break;
+                }
+                push_group(&mut groups, group, group_start..indices.len());
+                group_start = indices.len();
+                group_index += 1;
+            }
+            if keep(triangle) {
+                indices.extend(triangle);
+            }
+        }
+        for group in &self.groups[group_index.min(self.groups.len())..] {
+            push_group(&mut groups, group, group_start..indices.len());
+            group_start = indices.len();
+        }
+
+        let removed = (self.indices.len() - indices.len()) / 3;
+        self.indices = indices;
+        self.groups = groups;
+        removed
+    }
+
+    // every attribute of a vertex as bits, so equal vertices hash alike
+    fn vertex_bits(&self, index: usize) -> [u32; 12] {
+        let position = self.positions[index];
+        let normal = self.normals.get(index).copied().unwrap_or(Vec3::ZERO);
+        let texcoord = self.texcoords.get(index).copied().unwrap_or([0.0; 2]);
+        let tangent = self.tangents.get(index).copied().unwrap_or(Vec4::ZERO);
+        [
+            position.x,
+            position.y,
+            position.z,
+            normal.x,
+            normal.y,
+            normal.z,
+            texcoord[0],
+            texcoord[1],
+            tangent.x,
+            tangent.y,
+            tangent.z,
+            tangent.w,
+        ]
+        .map(f32::to_bits)
+    }
+}
+
+// an attribute without the unused vertices' values, an empty one stays empty
+fn retain_used<T>(attribute: &mut Vec<T>, used: &[bool]) {
+    let mut used = used.iter();
+    attribute.retain(|_| used.next().copied().unwrap_or_default());
+}
+
+// groups that lost all their triangles go away
+fn push_group(
+    groups: &mut Vec<super::Group>,
+    group: &super::Group,
+    indices: std::ops::Range<usize>,
+) {
+    if !indices.is_empty() {
+        groups.push(super::Group {
+            indices,
+            ..group.clone()
+        });
+    }
+}
+
+fn vertex_index(index: usize) -> u32 {
+    u32::try_from(index).expect("more than 2^32 vertices")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Group;
+    use crate::teapot;
+
+    // the teapot as baked, with the zero vertex 0 and indices from 1
+    fn raw_teapot() -> Mesh {
+        let indices: Vec<u32> = teapot::INDICES.iter().map(|&i| u32::from(i)).collect();
+        Mesh {
+            positions: teapot::VERTICES
+                .iter()
+                .map(teapot::Vertex::position)
+                .collect(),
+            normals: teapot::NORMALS.iter().map(teapot::Normal::normal).collect(),
+            texcoords: Vec::new(),
+            tangents: Vec::new(),
+            groups: vec![Group {
+                name: "teapot".to_owned(),
+                material: None,
+                indices: 0..indices.len(),
+            }],
+            indices,
+        }
+    }
+
+    fn triangle_soup(indices: Vec<u32>) -> Mesh {
+        Mesh {
+            positions: vec![
+                Vec3::ZERO,
+                Vec3::X,
+                Vec3::Y,
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+            ],
+            groups: vec![Group {
+                name: String::new(),
+                material: None,
+                indices: 0..indices.len(),
+            }],
+            indices,
+            ..Mesh::default()
+        }
+    }
+
+    #[test]
+    fn the_baked_teapot_has_a_zero_vertex_and_pinched_poles() {
+        let report = raw_teapot().validate();
+        assert!(report.out_of_range_indices.is_empty());
+        assert_eq!(report.unused_vertices, [0]);
+        assert_eq!(report.zero_length_normals, [0]);
+        // where the surface patches pinch to a point, one corner of each quad collapses
+        assert_eq!(report.degenerate_triangles.len(), 32);
+        assert!(report.non_manifold_edges.is_empty());
+    }
+
+    #[test]
+    fn repairing_the_baked_teapot_gives_the_built_in_one() {
+        let mut mesh = raw_teapot();
+        let degenerate = mesh.validate().degenerate_triangles.len();
+        let repairs = mesh.repair();
+        assert_eq!(repairs.removed_triangles, degenerate);
+        // at least the zero vertex
+        assert!(repairs.removed_vertices > repairs.merged_vertices);
+
+        let mut teapot = Mesh::teapot();
+        teapot.remove_degenerate_triangles();
+        teapot.merge_duplicate_vertices();
+        teapot.remove_unused_vertices();
+        assert_eq!(mesh, teapot);
+        assert!(mesh.validate().unused_vertices.is_empty());
+        assert!(mesh.validate().degenerate_triangles.is_empty());
+    }
+
+    #[test]
+    fn validate_finds_each_problem() {
+        let mut mesh = triangle_soup(vec![0, 1, 2, 1, 1, 3, 0, 1, 9, 0, 1, 4]);
+        mesh.normals = vec![Vec3::Z, Vec3::Z, Vec3::ZERO, Vec3::Z, Vec3::Z];
+        let report = mesh.validate();
+        assert_eq!(report.out_of_range_indices, [8]);
+        // a repeated corner, and three points on the x axis
+        assert_eq!(report.degenerate_triangles, [1, 3]);
+        assert_eq!(report.zero_length_normals, [2]);
+        assert!(report.unused_vertices.is_empty());
+        assert!(!report.is_clean());
+
+        let fan = triangle_soup(vec![0, 1, 2, 1, 0, 3, 0, 1, 3]);
+        assert_eq!(fan.validate().non_manifold_edges, [[0, 1]]);
+    }
+
+    #[test]
+    fn removing_triangles_shrinks_the_groups() {
+        let mut mesh = triangle_soup(vec![0, 1, 2, 1, 1, 3, 0, 2, 3, 2, 2, 2]);
+        mesh.groups = vec![
+            Group {
+                name: "a".to_owned(),
+                material: None,
+                indices: 0..6,
+            },
+            Group {
+                name: "b".to_owned(),
+                material: None,
+                indices: 6..9,
+            },
+            Group {
+                name: "c".to_owned(),
+                material: None,
+                indices: 9..12,
+            },
+        ];
+        assert_eq!(mesh.remove_degenerate_triangles(), 2);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        let groups: Vec<_> = mesh
+            .groups
+            .iter()
+            .map(|group| (group.name.as_str(), group.indices.clone()))
+            .collect();
+        assert_eq!(groups, [("a", 0..3), ("b", 3..6)]);
+
+        assert_eq!(mesh.remove_unused_vertices(), 1);
+        assert_eq!(mesh.positions.len(), 4);
+    }
+}
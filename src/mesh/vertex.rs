@@ -0,0 +1,174 @@
+// the interleaved formats meshes are uploaded in, the smallest one that holds what a mesh has:
+// the teapot only needs positions and normals, normal mapping adds texture coordinates and
+// tangents
+use super::Mesh;
+use crate::matrices::{Vec3, Vec4};
+use crate::teapot;
+use glium::backend::Facade;
+use glium::vertex::{BufferCreationError, VertexBuffer, VertexBufferAny};
+
+// position and normal, 24 bytes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LitVertex {
+    position: (f32, f32, f32),
+    normal: (f32, f32, f32),
+}
+
+implement_vertex!(LitVertex, position, normal);
+
+impl LitVertex {
+    #[must_use]
+    pub const fn new(position: Vec3, normal: Vec3) -> Self {
+        Self {
+            position: (position.x, position.y, position.z),
+            normal: (normal.x, normal.y, normal.z),
+        }
+    }
+
+    // one vertex of the split `teapot::VERTICES` and `teapot::NORMALS` layout
+    #[must_use]
+    pub const fn from_split(vertex: &teapot::Vertex, normal: &teapot::Normal) -> Self {
+        Self::new(vertex.position(), normal.normal())
+    }
+
+    #[must_use]
+    pub const fn position(&self) -> Vec3 {
+        Vec3::new(self.position.0, self.position.1, self.position.2)
+    }
+
+    #[must_use]
+    pub const fn normal(&self) -> Vec3 {
+        Vec3::new(self.normal.0, self.normal.1, self.normal.2)
+    }
+}
+
+// position, normal, texture coordinates and tangent, 48 bytes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MappedVertex {
+    position: (f32, f32, f32),
+    normal: (f32, f32, f32),
+    uv: (f32, f32),
+    tangent: (f32, f32, f32, f32),
+}
+
+implement_vertex!(MappedVertex, position, normal, uv, tangent);
+
+impl MappedVertex {
+    #[must_use]
+    pub const fn new(position: Vec3, normal: Vec3, uv: [f32; 2], tangent: Vec4) -> Self {
+        Self {
+            position: (position.x, position.y, position.z),
+            normal: (normal.x, normal.y, normal.z),
+            uv: (uv[0], uv[1]),
+            tangent: (tangent.x, tangent.y, tangent.z, tangent.w),
+        }
+    }
+
+    #[must_use]
+    pub const fn position(&self) -> Vec3 {
+        Vec3::new(self.position.0, self.position.1, self.position.2)
+    }
+
+    #[must_use]
+    pub const fn normal(&self) -> Vec3 {
+        Vec3::new(self.normal.0, self.normal.1, self.normal.2)
+    }
+}
+
+/// Zips the split position and normal arrays into interleaved vertices.
+///
+/// # Panics
+/// If the arrays have different lengths.
+#[must_use]
+pub fn interleave(vertices: &[teapot::Vertex], normals: &[teapot::Normal]) -> Vec<LitVertex> {
+    assert_eq!(vertices.len(), normals.len());
+    vertices
+        .iter()
+        .zip(normals)
+        .map(|(vertex, normal)| LitVertex::from_split(vertex, normal))
+        .collect()
+}
+
+impl Mesh {
+    // true if the mesh has texture coordinates or tangents, which need `MappedVertex`
+    #[must_use]
+    pub const fn is_mapped(&self) -> bool {
+        !self.texcoords.is_empty() || !self.tangents.is_empty()
+    }
+
+    // positions and normals, zero normals for a mesh without them
+    #[must_use]
+    pub fn lit_vertices(&self) -> Vec<LitVertex> {
+        (0..self.positions.len())
+            .map(|index| LitVertex::new(self.positions[index], self.normal_or_zero(index)))
+            .collect()
+    }
+
+    // every attribute, zero where the mesh doesn't have it
+    #[must_use]
+    pub fn mapped_vertices(&self) -> Vec<MappedVertex> {
+        (0..self.positions.len())
+            .map(|index| {
+                MappedVertex::new(
+                    self.positions[index],
+                    self.normal_or_zero(index),
+                    self.texcoords.get(index).copied().unwrap_or_default(),
+                    self.tangents.get(index).copied().unwrap_or(Vec4::ZERO),
+                )
+            })
+            .collect()
+    }
+
+    /// Uploads the vertices to `facade`'s context, as `MappedVertex` if the mesh is mapped and
+    /// as `LitVertex` otherwise.
+    ///
+    /// # Errors
+    /// If the context can't create the buffer.
+    pub fn vertex_buffer(
+        &self,
+        facade: &impl Facade,
+    ) -> Result<VertexBufferAny, BufferCreationError> {
+        Ok(if self.is_mapped() {
+            VertexBuffer::new(facade, &self.mapped_vertices())?.into()
+        } else {
+            VertexBuffer::new(facade, &self.lit_vertices())?.into()
+        })
+    }
+
+    fn normal_or_zero(&self, index: usize) -> Vec3 {
+        self.normals.get(index).copied().unwrap_or(Vec3::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_teapot_is_lit_vertices_only() {
+        let teapot = Mesh::teapot();
+        assert!(!teapot.is_mapped());
+        assert_eq!(std::mem::size_of::<LitVertex>(), 24);
+        assert_eq!(
+            teapot.lit_vertices(),
+            interleave(&teapot::VERTICES[1..], &teapot::NORMALS[1..])
+        );
+    }
+
+    #[test]
+    fn mapped_vertices_carry_every_attribute() {
+        let tangent = Vec4::new(1.0, 0.0, 0.0, -1.0);
+        let mesh = Mesh {
+            positions: vec![Vec3::X, Vec3::Y],
+            texcoords: vec![[0.5, 0.25]; 2],
+            tangents: vec![tangent; 2],
+            ..Mesh::default()
+        };
+        assert!(mesh.is_mapped());
+        assert_eq!(std::mem::size_of::<MappedVertex>(), 48);
+        assert_eq!(
+            mesh.mapped_vertices()[1],
+            MappedVertex::new(Vec3::Y, Vec3::ZERO, [0.5, 0.25], tangent)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::camera::Camera;
+use crate::matrices::{Mat4, Quat, Vec3};
+use crate::mesh::Mesh;
+use crate::shaders;
+use crate::transform::Transform;
+use glium::backend::Facade;
+use glium::draw_parameters::{BackfaceCullingMode, DepthTest};
+use glium::Surface;
+
+// where the teapot sits in the world, in front of the default camera at (0, 0, -2)
+pub const TEAPOT_CENTER: Vec3 = Vec3::new(0.0, 0.0, 0.8);
+
+// light vector (or position?)
+pub const LIGHT: Vec3 = Vec3::new(-0.9, 1.0, -0.2);
+
+// a nice blue
+pub const CLEAR_COLOR: (f32, f32, f32, f32) = (0.0, 0.4, 0.7, 1.0);
+
+// model transform of the teapot turned by `rotation` radians about its vertical axis
+#[must_use]
+pub fn teapot_transform(rotation: f32) -> Transform {
+    Transform::from_translation(TEAPOT_CENTER)
+        .with_rotation(Quat::from_axis_angle(Vec3::Y, -rotation))
+        .with_scale(Vec3::splat(0.008))
+}
+
+// reversed-z puts the near plane at depth 1.0, so the comparison and the cleared depth flip
+#[must_use]
+pub const fn depth_test(camera: &Camera) -> (DepthTest, f32) {
+    if camera.projection.is_reversed_z() {
+        (DepthTest::IfMore, 0.0)
+    } else {
+        (DepthTest::IfLess, 1.0)
+    }
+}
+
+// moves and scales `mesh` so its bounding box is centered on the teapot's and just as large,
+// which makes any model fit the scene's camera and transform
+pub fn fit_to_teapot(mesh: &mut Mesh) {
+    let (Some((min, max)), Some((teapot_min, teapot_max))) =
+        (mesh.bounds(), Mesh::teapot().bounds())
+    else {
+        return;
+    };
+    let largest = |size: Vec3| size.x.max(size.y).max(size.z);
+    let size = largest(max - min);
+    if size <= 0.0 {
+        return;
+    }
+    let scale = largest(teapot_max - teapot_min) / size;
+    let center = (min + max) * 0.5;
+    let teapot_center = (teapot_min + teapot_max) * 0.5;
+    mesh.transform(
+        &(Mat4::translation(teapot_center.x, teapot_center.y, teapot_center.z)
+            * Mat4::scale(scale)
+            * Mat4::translation(-center.x, -center.y, -center.z)),
+    );
+}
+
+// triangles facing away from the camera are clockwise on screen
+pub const BACKFACE_CULLING: BackfaceCullingMode = BackfaceCullingMode::CullClockwise;
+
+// the lit teapot on a blue background, shared by the window and the headless renderer;
+// the software renderer draws the same scene from the constants above
+pub struct TeapotScene {
+    // positions and normals, with texture coordinates and tangents for mapped meshes, interleaved;
+    // one part unless the mesh needs u32 indices and the context only has u16 ones
+    parts: Vec<(glium::vertex::VertexBufferAny, glium::index::IndexBufferAny)>,
+    program: glium::Program,
+}
+
+impl TeapotScene {
+    /// Uploads the teapot and compiles the shaders in `facade`'s context.
+    ///
+    /// # Panics
+    /// If the buffers can't be created or the shaders don't compile.
+    pub fn new(facade: &impl Facade) -> Self {
+        Self::with_mesh(facade, &Mesh::teapot())
+    }
+
+    /// `new` with another mesh in the teapot's place, see `fit_to_teapot` for getting it there.
+    ///
+    /// # Panics
+    /// If the buffers can't be created or the shaders don't compile.
+    pub fn with_mesh(facade: &impl Facade, mesh: &Mesh) -> Self {
+        let upload = |mesh: &Mesh| {
+            let vertices = mesh.vertex_buffer(facade).unwrap();
+            let indices = mesh
+                .narrow_indices()
+                .index_buffer(facade, glium::index::PrimitiveType::TrianglesList);
+            indices.map(|indices| (vertices, indices))
+        };
+        let parts = match upload(mesh) {
+            Ok(part) => vec![part],
+            Err(glium::index::BufferCreationError::IndexTypeNotSupported) => mesh
+                .u16_chunks()
+                .iter()
+                .map(|chunk| upload(chunk).unwrap())
+                .collect(),
+            Err(error) => panic!("{error}"),
+        };
+
+        // program
+        let program = glium::Program::from_source(
+            facade,
+            shaders::VERTEX_SHADER,
+            shaders::FRAGMENT_SHADER,
+            None,
+        )
+        .unwrap();
+
+        Self { parts, program }
+    }
+
+    /// Clears `target` and draws the teapot turned by `rotation` radians about its vertical axis.
+    /// The camera's aspect ratio is left to the caller.
+    ///
+    /// # Panics
+    /// If the camera's view has no inverse (a degenerate orientation) or drawing fails.
+    pub fn draw(&self, target: &mut impl Surface, camera: &Camera, rotation: f32) {
+        // transforms
+        let view_matrix = camera.view_matrix();
+        let model_view_matrix = view_matrix * teapot_transform(rotation).matrix();
+        let normal_matrix = model_view_matrix.normal_matrix().unwrap();
+
+        let perspective_matrix = camera.projection_matrix();
+
+        let (depth_test, clear_depth) = depth_test(camera);
+
+        // clear screen
+        target.clear_color_and_depth(CLEAR_COLOR, clear_depth);
+
+        // draw
+        let uniforms = uniform! {
+            u_light: LIGHT,
+            u_perspective: perspective_matrix,
+            model_view: model_view_matrix,
+            u_normal_matrix: normal_matrix,
+        };
+        let parameters = glium::DrawParameters {
+            depth: glium::Depth {
+                test: depth_test,
+                write: true,
+                ..glium::Depth::default()
+            },
+            backface_culling: BACKFACE_CULLING,
+            ..glium::DrawParameters::default()
+        };
+        for (vertices, indices) in &self.parts {
+            target
+                .draw(vertices, indices, &self.program, &uniforms, &parameters)
+                .unwrap();
+        }
+    }
+}
@@ -1,4 +1,4 @@
-pub const VERTEX_SHADER: &str = r#"
+pub const VERTEX_SHADER: &str = r"
 #version 150
 
 in vec3 position;
@@ -9,17 +9,18 @@ out vec3 v_position;
 
 uniform mat4 u_perspective;
 uniform mat4 model_view;
+uniform mat3 u_normal_matrix;
 
-void main() {    
-    // v_normal should be scaled if we non-uniformely scale positions.
-    v_normal = transpose(inverse(mat3(model_view))) * normal;    
+void main() {
+    // transpose(inverse(mat3(model_view))), computed once per draw on the CPU
+    v_normal = u_normal_matrix * normal;
 
     gl_Position = u_perspective * model_view *  vec4(position, 1.0);
     v_position = gl_Position.xyz / gl_Position.w;
 }
-"#;
+";
 
-pub const FRAGMENT_SHADER: &str = r#"
+pub const FRAGMENT_SHADER: &str = r"
 #version 140
 
 in vec3 v_normal;
@@ -52,4 +53,4 @@ void main() {
 
     color = vec4(ambient_color + scaled_diffuse_color + scaled_specular_color, 1.0);
 }
-"#;
+";
@@ -0,0 +1,489 @@
+use crate::camera::Camera;
+use crate::image::Image;
+use crate::matrices::{Mat3, Mat4, Vec3, Vec4};
+use crate::mesh::Mesh;
+use crate::scene::{self, BACKFACE_CULLING, CLEAR_COLOR, LIGHT};
+use glium::draw_parameters::{BackfaceCullingMode, DepthTest};
+
+// a CPU stand-in for the GL pipeline: clip-space triangles in, RGBA and depth out.
+// It follows the GL rules where they affect the picture: clipping against the near and far
+// planes, counterclockwise front faces with y up, pixel centers at half coordinates with the
+// top-left fill rule, screen-space depth and perspective-correct varyings.
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    // rows from the bottom up, like the GL framebuffer
+    color: Vec<[f32; 4]>,
+    depth: Vec<f32>,
+}
+
+// the subset of `glium::DrawParameters` the scene uses
+#[derive(Copy, Clone, Debug)]
+pub struct DrawParameters {
+    pub depth_test: DepthTest,
+    pub depth_write: bool,
+    pub backface_culling: BackfaceCullingMode,
+}
+
+// what the vertex shader outputs: `gl_Position` and the values interpolated for the fragments
+#[derive(Copy, Clone, Debug)]
+pub struct ShadedVertex {
+    pub clip_position: Vec4,
+    pub varyings: Varyings,
+}
+
+// `v_normal` and `v_position` of the shaders
+#[derive(Copy, Clone, Debug)]
+pub struct Varyings {
+    pub normal: Vec3,
+    pub position: Vec3,
+}
+
+impl Varyings {
+    fn weighted(values: [Self; 3], weights: [f32; 3]) -> Self {
+        let [a, b, c] = values;
+        let [wa, wb, wc] = weights;
+        Self {
+            normal: a.normal * wa + b.normal * wb + c.normal * wc,
+            position: a.position * wa + b.position * wb + c.position * wc,
+        }
+    }
+
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Self::weighted([self, other, other], [1.0 - t, t, 0.0])
+    }
+}
+
+impl Framebuffer {
+    #[must_use]
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = width as usize * height as usize;
+        Self {
+            width,
+            height,
+            color: vec![[0.0; 4]; len],
+            depth: vec![1.0; len],
+        }
+    }
+
+    pub fn clear_color_and_depth(&mut self, color: (f32, f32, f32, f32), depth: f32) {
+        self.color.fill([color.0, color.1, color.2, color.3]);
+        self.depth.fill(depth);
+    }
+
+    // depth at a pixel, counting rows from the bottom like `gl_FragCoord`
+    #[must_use]
+    pub fn depth(&self, x: u32, y: u32) -> f32 {
+        self.depth[self.index(x, y)]
+    }
+
+    // converts to 8 bits per channel the way GL writes to a normalized framebuffer
+    #[must_use]
+    pub fn to_image(&self) -> Image {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let pixels: Vec<u8> = self
+            .color
+            .iter()
+            .flatten()
+            .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect();
+        Image::from_bottom_up(self.width, self.height, &pixels)
+    }
+
+    const fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Draws a triangle list, `fragment` turns the interpolated varyings into an RGBA color.
+    ///
+    /// # Panics
+    /// If an index is out of `vertices`' range.
+    pub fn draw<I, F>(
+        &mut self,
+        vertices: &[ShadedVertex],
+        indices: &[I],
+        parameters: DrawParameters,
+        fragment: F,
+    ) where
+        I: Copy + Into<u32>,
+        F: Fn(&Varyings) -> [f32; 4],
+    {
+        for triangle in indices.chunks_exact(3) {
+            let triangle = [0, 1, 2].map(|i| vertices[triangle[i].into() as usize]);
+            let polygon = clip_to_depth_range(&triangle);
+            // the clipped polygon is convex, fan it back into triangles
+            for i in 1..polygon.len().saturating_sub(1) {
+                self.rasterize(
+                    [polygon[0], polygon[i], polygon[i + 1]],
+                    parameters,
+                    &fragment,
+                );
+            }
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn rasterize<F>(
+        &mut self,
+        triangle: [ShadedVertex; 3],
+        parameters: DrawParameters,
+        fragment: &F,
+    ) where
+        F: Fn(&Varyings) -> [f32; 4],
+    {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let mut window = triangle.map(|vertex| {
+            let clip = vertex.clip_position;
+            let inverse_w = 1.0 / clip.w;
+            WindowVertex {
+                x: clip.x.mul_add(inverse_w, 1.0) * 0.5 * width,
+                y: clip.y.mul_add(inverse_w, 1.0) * 0.5 * height,
+                depth: (clip.z * inverse_w).mul_add(0.5, 0.5),
+                inverse_w,
+                varyings: vertex.varyings,
+            }
+        });
+
+        // twice the signed area, positive for counterclockwise triangles
+        let area = edge(&window[0], &window[1], window[2].x, window[2].y);
+        let culled = match parameters.backface_culling {
+            BackfaceCullingMode::CullingDisabled => false,
+            BackfaceCullingMode::CullClockwise => area < 0.0,
+            BackfaceCullingMode::CullCounterClockwise => area > 0.0,
+        };
+        if culled || area == 0.0 || !area.is_finite() {
+            return;
+        }
+        // from here on the triangle is counterclockwise, which the edge tests below expect
+        if area < 0.0 {
+            window.swap(1, 2);
+        }
+        let area = area.abs();
+
+        let min_x = window.iter().map(|v| v.x).fold(f32::INFINITY, f32::min);
+        let max_x = window.iter().map(|v| v.x).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = window.iter().map(|v| v.y).fold(f32::INFINITY, f32::min);
+        let max_y = window.iter().map(|v| v.y).fold(f32::NEG_INFINITY, f32::max);
+        let (x_range, y_range) = (
+            pixel_range(min_x, max_x, self.width),
+            pixel_range(min_y, max_y, self.height),
+        );
+
+        let [a, b, c] = &window;
+        for y in y_range {
+            let center_y = y as f32 + 0.5;
+            for x in x_range.clone() {
+                let center_x = x as f32 + 0.5;
+                // each weight is the edge function of the opposite edge
+                let weights = [
+                    (edge(b, c, center_x, center_y), b, c),
+                    (edge(c, a, center_x, center_y), c, a),
+                    (edge(a, b, center_x, center_y), a, b),
+                ];
+                if !weights.iter().all(|&(weight, from, to)| {
+                    weight > 0.0 || (weight == 0.0 && is_top_left(from, to))
+                }) {
+                    continue;
+                }
+                let barycentric = weights.map(|(weight, _, _)| weight / area);
+
+                let depth = a.depth.mul_add(
+                    barycentric[0],
+                    b.depth.mul_add(barycentric[1], c.depth * barycentric[2]),
+                );
+                let index = self.index(x, y);
+                if !passes(parameters.depth_test, depth, self.depth[index]) {
+                    continue;
+                }
+
+                // perspective-correct weights for the varyings
+                let corrected = [
+                    barycentric[0] * a.inverse_w,
+                    barycentric[1] * b.inverse_w,
+                    barycentric[2] * c.inverse_w,
+                ];
+                let sum: f32 = corrected.iter().sum();
+                let varyings = Varyings::weighted(
+                    [a.varyings, b.varyings, c.varyings],
+                    corrected.map(|weight| weight / sum),
+                );
+
+                self.color[index] = fragment(&varyings);
+                if parameters.depth_write {
+                    self.depth[index] = depth;
+                }
+            }
+        }
+    }
+}
+
+struct WindowVertex {
+    // GL window coordinates, y up
+    x: f32,
+    y: f32,
+    depth: f32,
+    inverse_w: f32,
+    varyings: Varyings,
+}
+
+// positive when (x, y) is left of the edge from `from` to `to`
+fn edge(from: &WindowVertex, to: &WindowVertex, x: f32, y: f32) -> f32 {
+    (to.x - from.x).mul_add(y - from.y, -((to.y - from.y) * (x - from.x)))
+}
+
+// pixels on a shared edge belong to the triangle that has it as a top or left edge,
+// so they're drawn exactly once; with counterclockwise winding and y up, top edges run
+// right to left and left edges run downwards
+#[allow(clippy::float_cmp)]
+fn is_top_left(from: &WindowVertex, to: &WindowVertex) -> bool {
+    (from.y == to.y && to.x < from.x) || to.y < from.y
+}
+
+// pixels whose centers can fall between `min` and `max`, clamped to the framebuffer
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn pixel_range(min: f32, max: f32, size: u32) -> std::ops::Range<u32> {
+    let start = (min - 0.5).ceil().max(0.0) as u32;
+    let end = ((max - 0.5).floor() + 1.0).clamp(0.0, size as f32) as u32;
+    start..end.max(start)
+}
+
+// exact like GL, `IfEqual` is for redrawing the same geometry
+#[allow(clippy::float_cmp)]
+fn passes(test: DepthTest, depth: f32, stored: f32) -> bool {
+    match test {
+        DepthTest::Ignore => false,
+        DepthTest::Overwrite => true,
+        DepthTest::IfEqual => depth == stored,
+        DepthTest::IfNotEqual => depth != stored,
+        DepthTest::IfMore => depth > stored,
+        DepthTest::IfMoreOrEqual => depth >= stored,
+        DepthTest::IfLess => depth < stored,
+        DepthTest::IfLessOrEqual => depth <= stored,
+    }
+}
+
+// Sutherland-Hodgman against -w <= z <= w; x and y are left to the pixel loop, which only
+// visits pixels inside the framebuffer anyway
+fn clip_to_depth_range(triangle: &[ShadedVertex; 3]) -> Vec<ShadedVertex> {
+    let near = |v: &ShadedVertex| v.clip_position.w + v.clip_position.z;
+    let far = |v: &ShadedVertex| v.clip_position.w - v.clip_position.z;
+    let polygon = clip_polygon(triangle.to_vec(), near);
+    clip_polygon(polygon, far)
+}
+
+// keeps the part of the polygon where `distance` is positive
+fn clip_polygon(
+    polygon: Vec<ShadedVertex>,
+    distance: impl Fn(&ShadedVertex) -> f32,
+) -> Vec<ShadedVertex> {
+    if polygon.iter().all(|vertex| distance(vertex) >= 0.0) {
+        return polygon;
+    }
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let (d_current, d_next) = (distance(current), distance(next));
+        if d_current >= 0.0 {
+            clipped.push(*current);
+        }
+        if (d_current >= 0.0) != (d_next >= 0.0) {
+            let t = d_current / (d_current - d_next);
+            clipped.push(ShadedVertex {
+                clip_position: current.clip_position
+                    + (next.clip_position - current.clip_position) * t,
+                varyings: current.varyings.lerp(next.varyings, t),
+            });
+        }
+    }
+    clipped
+}
+
+// the uniforms of `shaders::VERTEX_SHADER` and `shaders::FRAGMENT_SHADER`
+pub struct Uniforms {
+    pub perspective: Mat4,
+    pub model_view: Mat4,
+    pub normal_matrix: Mat3,
+    pub light: Vec3,
+}
+
+// port of `shaders::VERTEX_SHADER`
+#[must_use]
+pub fn vertex_shader(uniforms: &Uniforms, position: Vec3, normal: Vec3) -> ShadedVertex {
+    let clip_position = uniforms.perspective * (uniforms.model_view * position.extend(1.0));
+    ShadedVertex {
+        clip_position,
+        varyings: Varyings {
+            normal: uniforms.normal_matrix * normal,
+            position: clip_position.truncate() * (1.0 / clip_position.w),
+        },
+    }
+}
+
+// port of `shaders::FRAGMENT_SHADER`, Blinn-Phong with a directional light
+#[must_use]
+pub fn fragment_shader(uniforms: &Uniforms, varyings: &Varyings) -> [f32; 4] {
+    const AMBIENT_COLOR: Vec3 = Vec3::new(0.30, 0.10, 0.20);
+    const DIFFUSE_COLOR: Vec3 = Vec3::new(0.80, 0.50, 0.60);
+    const SPECULAR_COLOR: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+
+    let light = uniforms.light.normalized();
+    let normal = varyings.normal.normalized();
+
+    let camera_direction = -varyings.position.normalized();
+    let half_direction = (light + camera_direction).normalized();
+
+    let diffuse_intensity = normal.dot(light).max(-0.35);
+    let specular_intensity = half_direction.dot(normal).max(0.0).powi(16);
+
+    let color =
+        AMBIENT_COLOR + DIFFUSE_COLOR * diffuse_intensity + SPECULAR_COLOR * specular_intensity;
+    [color.x, color.y, color.z, 1.0]
+}
+
+/// The software counterpart of `TeapotScene::draw`: clears `target` and draws the teapot turned
+/// by `rotation` radians. The camera's aspect ratio is left to the caller.
+///
+/// # Panics
+/// If the camera's view has no inverse (a degenerate orientation).
+pub fn draw_teapot(target: &mut Framebuffer, camera: &Camera, rotation: f32) {
+    draw_mesh(target, camera, rotation, &Mesh::teapot());
+}
+
+/// `draw_teapot` with another mesh in the teapot's place.
+///
+/// # Panics
+/// If the camera's view has no inverse (a degenerate orientation).
+pub fn draw_mesh(target: &mut Framebuffer, camera: &Camera, rotation: f32, mesh: &Mesh) {
+    let model_view = camera.view_matrix() * scene::teapot_transform(rotation).matrix();
+    let uniforms = Uniforms {
+        perspective: camera.projection_matrix(),
+        model_view,
+        normal_matrix: model_view.normal_matrix().unwrap(),
+        light: LIGHT,
+    };
+
+    let normals = mesh
+        .normals
+        .iter()
+        .copied()
+        .chain(std::iter::repeat(Vec3::ZERO));
+    let vertices: Vec<ShadedVertex> = mesh
+        .positions
+        .iter()
+        .zip(normals)
+        .map(|(&position, normal)| vertex_shader(&uniforms, position, normal))
+        .collect();
+
+    let (depth_test, clear_depth) = scene::depth_test(camera);
+    target.clear_color_and_depth(CLEAR_COLOR, clear_depth);
+    target.draw(
+        &vertices,
+        &mesh.indices,
+        DrawParameters {
+            depth_test,
+            depth_write: true,
+            backface_culling: BACKFACE_CULLING,
+        },
+        |varyings| fragment_shader(&uniforms, varyings),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMETERS: DrawParameters = DrawParameters {
+        depth_test: DepthTest::IfLess,
+        depth_write: true,
+        backface_culling: BackfaceCullingMode::CullClockwise,
+    };
+
+    fn vertex(x: f32, y: f32, z: f32) -> ShadedVertex {
+        ShadedVertex {
+            clip_position: Vec4::new(x, y, z, 1.0),
+            varyings: Varyings {
+                normal: Vec3::ZERO,
+                position: Vec3::ZERO,
+            },
+        }
+    }
+
+    fn coverage(framebuffer: &Framebuffer) -> usize {
+        framebuffer
+            .color
+            .iter()
+            .filter(|color| color[0] > 0.0)
+            .count()
+    }
+
+    #[test]
+    fn shared_edges_are_drawn_once() {
+        // a full-screen quad out of two triangles, counting how often each pixel is written
+        let mut framebuffer = Framebuffer::new(8, 8);
+        let vertices = [
+            vertex(-1.0, -1.0, 0.0),
+            vertex(1.0, -1.0, 0.0),
+            vertex(1.0, 1.0, 0.0),
+            vertex(-1.0, 1.0, 0.0),
+        ];
+        let parameters = DrawParameters {
+            depth_test: DepthTest::Overwrite,
+            ..PARAMETERS
+        };
+        let writes = std::cell::Cell::new(0);
+        framebuffer.draw(&vertices, &[0_u16, 1, 2, 0, 2, 3], parameters, |_| {
+            writes.set(writes.get() + 1);
+            [1.0; 4]
+        });
+        assert_eq!(writes.get(), 64);
+        assert_eq!(coverage(&framebuffer), 64);
+    }
+
+    #[test]
+    fn clockwise_triangles_are_culled() {
+        let mut framebuffer = Framebuffer::new(8, 8);
+        let vertices = [
+            vertex(-1.0, -1.0, 0.0),
+            vertex(1.0, -1.0, 0.0),
+            vertex(1.0, 1.0, 0.0),
+        ];
+        framebuffer.draw(&vertices, &[0_u16, 2, 1], PARAMETERS, |_| [1.0; 4]);
+        assert_eq!(coverage(&framebuffer), 0);
+        framebuffer.draw(&vertices, &[0_u16, 1, 2], PARAMETERS, |_| [1.0; 4]);
+        // the lower right half, the pixels on the diagonal belong to it as it's the left edge
+        assert_eq!(coverage(&framebuffer), 36);
+    }
+
+    #[test]
+    fn nearer_triangles_win() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        let near = [
+            vertex(-1.0, -1.0, -0.5),
+            vertex(3.0, -1.0, -0.5),
+            vertex(-1.0, 3.0, -0.5),
+        ];
+        let far = near.map(|v| vertex(v.clip_position.x, v.clip_position.y, 0.5));
+        framebuffer.draw(&near, &[0_u16, 1, 2], PARAMETERS, |_| [1.0, 0.0, 0.0, 1.0]);
+        framebuffer.draw(&far, &[0_u16, 1, 2], PARAMETERS, |_| [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(framebuffer.to_image().pixel(1, 1), [255, 0, 0, 255]);
+        assert!((framebuffer.depth(1, 1) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn triangles_behind_the_near_plane_are_clipped() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        // half of the triangle is in front of the near plane (z < -w)
+        let vertices = [
+            vertex(-1.0, -1.0, -3.0),
+            vertex(3.0, -1.0, 1.0),
+            vertex(-1.0, 3.0, 1.0),
+        ];
+        framebuffer.draw(&vertices, &[0_u16, 1, 2], PARAMETERS, |_| [1.0; 4]);
+        assert!(coverage(&framebuffer) > 0);
+        assert!(framebuffer.depth.iter().all(|&depth| depth >= 0.0));
+    }
+}
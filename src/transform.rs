@@ -0,0 +1,169 @@
+use crate::matrices::{Mat4, Quat, Vec3};
+
+// placement of a single object, composed as translation * rotation * scale,
+// i.e. scale first, then rotate, then translate
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::splat(1.0),
+    };
+
+    #[must_use]
+    pub const fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    #[must_use]
+    pub const fn with_rotation(self, rotation: Quat) -> Self {
+        Self { rotation, ..self }
+    }
+
+    #[must_use]
+    pub const fn with_scale(self, scale: Vec3) -> Self {
+        Self { scale, ..self }
+    }
+
+    #[must_use]
+    pub fn matrix(&self) -> Mat4 {
+        // T * R * S written out directly: rotation columns scaled per axis, translation in the last column
+        let r = Mat4::from(self.rotation);
+        let s = self.scale;
+        Mat4::from_cols(
+            r.col(0) * s.x,
+            r.col(1) * s.y,
+            r.col(2) * s.z,
+            self.translation.extend(1.0),
+        )
+    }
+
+    // inverse of `matrix` for matrices without shear or projection;
+    // a mirroring matrix (negative determinant) comes back with a negative x scale,
+    // an axis scaled to zero has no direction left and gets the one completing the other two,
+    // or the identity rotation if more than one is zero
+    #[must_use]
+    pub fn from_matrix(m: &Mat4) -> Self {
+        let translation = m.col(3).truncate();
+        let mut scale = Vec3::new(
+            m.col(0).truncate().length(),
+            m.col(1).truncate().length(),
+            m.col(2).truncate().length(),
+        );
+        if m.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+        let scales = [scale.x, scale.y, scale.z];
+        let axes =
+            [0, 1, 2].map(|i| (scales[i] != 0.0).then(|| m.col(i).truncate() * (1.0 / scales[i])));
+        let from_axes = |x: Vec3, y: Vec3, z: Vec3| {
+            Quat::from_rotation_matrix(&Mat4::from_cols(
+                x.extend(0.0),
+                y.extend(0.0),
+                z.extend(0.0),
+                Mat4::IDENTITY.col(3),
+            ))
+        };
+        let rotation = match axes {
+            [Some(x), Some(y), Some(z)] => from_axes(x, y, z),
+            [None, Some(y), Some(z)] => from_axes(y.cross(z), y, z),
+            [Some(x), None, Some(z)] => from_axes(x, z.cross(x), z),
+            [Some(x), Some(y), None] => from_axes(x, y, x.cross(y)),
+            _ => Quat::IDENTITY,
+        };
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<Transform> for Mat4 {
+    fn from(t: Transform) -> Self {
+        t.matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrices::{Axis, EulerOrder};
+
+    fn assert_mat_eq(a: &Mat4, b: &Mat4) {
+        for (col_a, col_b) in a.0.iter().zip(b.0) {
+            for (x, y) in col_a.iter().zip(col_b) {
+                assert!((x - y).abs() < 1e-5, "{a:?} != {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn matrix_is_translation_rotation_scale() {
+        let t = Transform::from_translation(Vec3::new(1.0, -2.0, 0.5))
+            .with_rotation(Quat::from_axis_angle(Vec3::Y, 0.6))
+            .with_scale(Vec3::new(2.0, 0.5, 3.0));
+        let expected = Mat4::translation(1.0, -2.0, 0.5)
+            * Mat4::rotation(Axis::Y, 0.6)
+            * Mat4::scale_xyz(2.0, 0.5, 3.0);
+        assert_mat_eq(&t.matrix(), &expected);
+    }
+
+    #[test]
+    fn from_matrix_round_trips() {
+        for scale in [
+            Vec3::splat(0.008),
+            Vec3::new(2.0, 0.5, 3.0),
+            Vec3::new(-1.0, 2.0, 1.0),
+        ] {
+            let t = Transform::from_translation(Vec3::new(1.0, -2.0, 0.5))
+                .with_rotation(Quat::from_euler(EulerOrder::XYZ, 0.3, -1.1, 2.0))
+                .with_scale(scale);
+            let decomposed = Transform::from_matrix(&t.matrix());
+            assert_mat_eq(&decomposed.matrix(), &t.matrix());
+            assert!((decomposed.translation - t.translation).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn from_matrix_keeps_the_rotation_finite_for_zero_scales() {
+        let rotation = Quat::from_euler(EulerOrder::XYZ, 0.3, -1.1, 2.0);
+        for scale in [
+            Vec3::new(0.0, 2.0, 3.0),
+            Vec3::new(2.0, 0.0, 3.0),
+            Vec3::new(2.0, 3.0, 0.0),
+            Vec3::ZERO,
+        ] {
+            let t = Transform::IDENTITY
+                .with_rotation(rotation)
+                .with_scale(scale);
+            let decomposed = Transform::from_matrix(&t.matrix());
+            let q = decomposed.rotation;
+            assert!(
+                [q.x, q.y, q.z, q.w].iter().all(|x| x.is_finite()),
+                "{scale:?}"
+            );
+            assert_mat_eq(&decomposed.matrix(), &t.matrix());
+        }
+        // with one axis left the others' directions can't be told apart
+        let t = Transform::IDENTITY
+            .with_rotation(rotation)
+            .with_scale(Vec3::new(0.0, 0.0, 3.0));
+        assert_eq!(Transform::from_matrix(&t.matrix()).rotation, Quat::IDENTITY);
+    }
+}
@@ -0,0 +1,147 @@
+// Renders the teapot scene headlessly with fixed cameras and rotations, through OpenGL and through
+// the software renderer, and compares the results against the reference images in tests/golden.
+// Without OpenGL only the software renders are checked. On a mismatch the rendered image and a diff
+// (mismatched pixels in red) are written next to each other in target/tmp/golden.
+//
+// After an intended change to the rendering, regenerate the references with
+//     UPDATE_GOLDEN=1 cargo test --test golden
+// and look over the new images before committing them.
+#![cfg(target_os = "linux")]
+
+use learn_opengl_with_glium::camera::Camera;
+use learn_opengl_with_glium::headless::Headless;
+use learn_opengl_with_glium::image::{self, Image};
+use learn_opengl_with_glium::matrices::{Projection, Vec3};
+use learn_opengl_with_glium::scene::{TeapotScene, TEAPOT_CENTER};
+use learn_opengl_with_glium::software::{self, Framebuffer};
+use std::f32::consts::TAU;
+use std::path::PathBuf;
+
+const SIZE: u32 = 256;
+
+// per pixel color difference that's still a match, see `image::compare`
+const THRESHOLD: f32 = 0.1;
+// share of pixels allowed over the threshold, for rasterization differences along edges
+// between drivers
+const MAX_MISMATCHED: f32 = 0.002;
+
+const PERSPECTIVE: Projection = Projection::Perspective {
+    fov_angle: TAU / 6.0,
+    z_near: 0.1,
+    z_far: 1024.0,
+};
+
+fn default_camera(projection: Projection) -> Camera {
+    Camera::new(Vec3::new(0.0, 0.0, -2.0), projection)
+}
+
+// None when there's no OpenGL to render with
+fn render_gl(camera: &Camera, rotation: f32) -> Option<Image> {
+    let headless = match Headless::new() {
+        Ok(headless) => headless,
+        Err(error) => {
+            eprintln!("skipping the OpenGL render, no headless context: {error}");
+            return None;
+        }
+    };
+    let scene = TeapotScene::new(&headless);
+    let mut camera = *camera;
+    camera.set_viewport(SIZE, SIZE);
+    Some(headless.render(SIZE, SIZE, |target| {
+        scene.draw(target, &camera, rotation);
+    }))
+}
+
+fn render_software(camera: &Camera, rotation: f32) -> Image {
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    let mut camera = *camera;
+    camera.set_viewport(SIZE, SIZE);
+    software::draw_teapot(&mut framebuffer, &camera, rotation);
+    framebuffer.to_image()
+}
+
+// renders the scene both ways and compares each against the reference, which comes from OpenGL
+fn check(name: &str, camera: &Camera, rotation: f32) {
+    let reference_path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("tests/golden/{name}.png"));
+    let gl = render_gl(camera, rotation);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let gl = gl.expect("updating the references needs OpenGL");
+        gl.save_png(&reference_path).unwrap();
+        return;
+    }
+    let expected = Image::load_png(&reference_path).unwrap_or_else(|error| {
+        panic!(
+            "couldn't load {}: {error}; run with UPDATE_GOLDEN=1 to create it",
+            reference_path.display()
+        )
+    });
+
+    if let Some(gl) = gl {
+        compare(name, &expected, &gl, MAX_MISMATCHED);
+    }
+    let software = render_software(camera, rotation);
+    compare(
+        &format!("{name}.software"),
+        &expected,
+        &software,
+        MAX_MISMATCHED,
+    );
+}
+
+// on a mismatch, writes the image and the diff to target/tmp/golden and panics
+fn compare(name: &str, expected: &Image, actual: &Image, max_mismatched: f32) {
+    let difference = image::compare(expected, actual, THRESHOLD);
+    if difference.mismatched_fraction() > max_mismatched {
+        let output_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let actual_path = output_dir.join(format!("{name}.png"));
+        let diff_path = output_dir.join(format!("{name}.diff.png"));
+        actual.save_png(&actual_path).unwrap();
+        difference.diff.save_png(&diff_path).unwrap();
+        panic!(
+            "{name}: {} pixels ({:.2}%) differ from the reference, see {} and {}",
+            difference.mismatched,
+            difference.mismatched_fraction() * 100.0,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn front() {
+    check("front", &default_camera(PERSPECTIVE), 0.0);
+}
+
+#[test]
+fn quarter_turn() {
+    check("quarter_turn", &default_camera(PERSPECTIVE), TAU / 4.0);
+}
+
+#[test]
+fn from_above() {
+    let camera = Camera::new(Vec3::new(0.0, 1.5, -0.6), PERSPECTIVE).looking_at(TEAPOT_CENTER);
+    check("from_above", &camera, TAU / 8.0);
+}
+
+#[test]
+fn orthographic() {
+    let projection = Projection::Orthographic {
+        height: 1.5,
+        z_near: 0.1,
+        z_far: 1024.0,
+    };
+    check("orthographic", &default_camera(projection), TAU / 3.0);
+}
+
+#[test]
+fn reversed_z() {
+    // not quite `front`: the shaders light the teapot from its position after projection
+    let projection = Projection::ReversedZ {
+        fov_angle: TAU / 6.0,
+        z_near: 0.1,
+        z_far: None,
+    };
+    check("reversed_z", &default_camera(projection), 0.0);
+}